@@ -4,19 +4,21 @@ use crate::ffs_api::models::{
 };
 
 use super::{
+    metrics::{BACKEND_ERRORS, BACKEND_REQUEST_DURATION, BACKEND_RETRIES},
     models::{
         contents_resource::ContentsResource, folder_creation_resource::FolderCreationResource,
         inode_resource::InodeResource, user_resource::UserResource,
     },
+    retry::{retry_after, RetryPolicy},
     ApiConfig, ApiError, Result,
 };
 use reqwest::{
-    header::{HeaderMap, HeaderValue, ACCEPT},
-    multipart, Response, StatusCode,
+    header::{HeaderMap, HeaderValue, ACCEPT, RANGE},
+    multipart, RequestBuilder, Response, StatusCode,
 };
 use serde::de::DeserializeOwned;
 use std::path::Path;
-use tokio::io::AsyncRead;
+use tokio::io::{sink, AsyncRead, AsyncReadExt};
 use tokio_util::io::{ReaderStream, StreamReader};
 use tracing::debug;
 
@@ -24,23 +26,35 @@ use tracing::debug;
 // futures::io::AsyncRead.
 use futures::stream::TryStreamExt;
 
+/// Hash the password the way the FileSystemService expects it for authentication
+pub fn hash_password(password: &str) -> String {
+    sha256::digest(format!("{}FileFighterWithSomeSalt", password)).to_uppercase()
+}
+
 pub async fn get_token_for_user(
     api_config: &ApiConfig,
     username: &str,
     password: &str,
+) -> Result<String> {
+    get_token_for_password_hash(api_config, username, &hash_password(password)).await
+}
+
+/// Same as [`get_token_for_user`] with a password already hashed by [`hash_password`]
+pub async fn get_token_for_password_hash(
+    api_config: &ApiConfig,
+    username: &str,
+    password_hash: &str,
 ) -> Result<String> {
     let url = format!("{}/user/authenticate", api_config.fss_base_url);
-    let password = sha256::digest(format!("{}FileFighterWithSomeSalt", password)).to_uppercase();
 
-    debug!("Authenticating with password '{}'", password);
+    debug!("Authenticating user '{}'", username);
 
-    let response = reqwest::Client::builder()
-        .cookie_store(true)
-        .build()?
+    let request = api_config
+        .client
         .post(url)
-        .basic_auth(username, Some(password))
-        .send()
-        .await?;
+        .timeout(api_config.request_timeout)
+        .basic_auth(username, Some(password_hash));
+    let response = send(api_config, request, "fss:/user/authenticate").await?;
 
     match response.status() {
         StatusCode::CREATED => Ok(response
@@ -51,10 +65,7 @@ pub async fn get_token_for_user(
             })?
             .value()
             .to_owned()),
-        code => Err(ApiError::ResponseMalformed(format!(
-            "Response Code was {}, but expected 201",
-            code
-        ))),
+        _ => Err(error_from_response(response).await),
     }
 }
 
@@ -63,11 +74,12 @@ pub async fn get_user_info(api_config: &ApiConfig, token: &str) -> Result<UserRe
 
     debug!("Getting user info with token '{}'", token);
 
-    let response = reqwest::Client::new()
+    let request = api_config
+        .client
         .get(url)
-        .bearer_auth(token)
-        .send()
-        .await?;
+        .timeout(api_config.request_timeout)
+        .bearer_auth(token);
+    let response = send_idempotent(api_config, request, "fss:/user/info").await?;
 
     transform_response(response, StatusCode::OK).await
 }
@@ -77,12 +89,13 @@ pub async fn get_inode(api_config: &ApiConfig, path: &Path, token: &str) -> Resu
 
     debug!("Getting inode by path '{}'", path.display());
 
-    let response = reqwest::Client::new()
+    let request = api_config
+        .client
         .get(url)
+        .timeout(api_config.request_timeout)
         .bearer_auth(token)
-        .header("X-FF-PATH", path.to_str().unwrap())
-        .send()
-        .await?;
+        .header("X-FF-PATH", path.to_str().unwrap());
+    let response = send_idempotent(api_config, request, "fss:/filesystem/info").await?;
 
     transform_response(response, StatusCode::OK).await
 }
@@ -96,12 +109,13 @@ pub async fn get_contents_of_folder(
 
     debug!("Authenticating with token '{}'", token);
 
-    let response = reqwest::Client::new()
+    let request = api_config
+        .client
         .get(url)
+        .timeout(api_config.request_timeout)
         .bearer_auth(token)
-        .header("X-FF-PATH", path.to_str().unwrap())
-        .send()
-        .await?;
+        .header("X-FF-PATH", path.to_str().unwrap());
+    let response = send_idempotent(api_config, request, "fss:/filesystem/contents").await?;
 
     transform_response(response, StatusCode::OK).await
 }
@@ -121,12 +135,13 @@ pub async fn create_directory(
         parent_path: parent_path.to_str().unwrap().to_owned(),
     };
 
-    let response = reqwest::Client::new()
+    let request = api_config
+        .client
         .post(url)
+        .timeout(api_config.request_timeout)
         .bearer_auth(token)
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let response = send(api_config, request, "fss:/filesystem/folder/create").await?;
 
     transform_response(response, StatusCode::CREATED).await
 }
@@ -146,12 +161,13 @@ pub async fn rename_inode(
         new_name: new_name.to_owned(),
     };
 
-    let response = reqwest::Client::new()
+    let request = api_config
+        .client
         .put(url)
+        .timeout(api_config.request_timeout)
         .bearer_auth(token)
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let response = send(api_config, request, "fss:/filesystem/rename").await?;
 
     transform_response(response, StatusCode::OK).await
 }
@@ -171,12 +187,13 @@ pub async fn move_inode(
         new_path: new_path.to_str().unwrap().to_owned(),
     };
 
-    let response = reqwest::Client::new()
+    let request = api_config
+        .client
         .put(url)
+        .timeout(api_config.request_timeout)
         .bearer_auth(token)
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let response = send(api_config, request, "fss:/filesystem/move").await?;
 
     transform_response(response, StatusCode::OK).await
 }
@@ -194,17 +211,26 @@ pub async fn delete_inode(
     let params = [("token", token)];
 
     let url = reqwest::Url::parse_with_params(&url, &params).unwrap();
-    let response = reqwest::Client::new().delete(url).send().await?;
+    let request = api_config
+        .client
+        .delete(url)
+        .timeout(api_config.request_timeout);
+    let response = send(api_config, request, "fhs:/delete").await?;
 
     transform_response(response, StatusCode::OK).await
 }
 
+/// Upload `bytes` as `new_name` in `parent_path`.
+///
+/// The MIME type is guessed from `final_name`, the name the file ends up with, which differs
+/// from `new_name` for uploads to a temporary name.
 // IDEA: fix usage of relative paths to uploaded files. (When to create parent folder and when not)
 pub async fn upload_file<ByteStream>(
     api_config: &ApiConfig,
     token: &str,
     parent_path: &Path,
     new_name: &str,
+    final_name: &str,
     bytes: ByteStream,
 ) -> Result<Vec<InodeResource>>
 where
@@ -232,26 +258,68 @@ where
     let some_file = multipart::Part::stream(reqwest::Body::wrap_stream(stream))
         .file_name("file")
         .mime_str(
-            new_mime_guess::from_path(new_name)
+            new_mime_guess::from_path(final_name)
                 .first_or_octet_stream()
                 .as_ref(),
         )?;
     let form = multipart::Form::new().part("file", some_file);
 
-    let response = reqwest::Client::new()
-        .post(url)
-        .multipart(form)
-        .headers(headers)
-        .send()
-        .await?;
+    let request = api_config.client.post(url).multipart(form).headers(headers);
+    let response = send(api_config, request, "fhs:/upload").await?;
 
     transform_response(response, StatusCode::OK).await
 }
 
+/// Upload the first `start_pos` bytes of the file `name` in `parent_path` followed by `bytes`
+/// as `new_name` in the same folder, with the MIME type of `name`.
+///
+/// The FileHandlerService has no append endpoint, so the prefix is downloaded and streamed in
+/// front of the new bytes. `new_name` must be another name than `name`, the
+/// FileHandlerService may replace the target before the prefix has been read completely.
+pub async fn upload_file_at_offset<ByteStream>(
+    api_config: &ApiConfig,
+    token: &str,
+    parent_path: &Path,
+    name: &str,
+    start_pos: u64,
+    new_name: &str,
+    bytes: ByteStream,
+) -> Result<Vec<InodeResource>>
+where
+    ByteStream: AsyncRead + Send + Sync + 'static + Unpin,
+{
+    let existing_path = &parent_path.join(name);
+    let existing = get_inode(api_config, existing_path, token).await?;
+
+    if existing.size < start_pos {
+        return Err(ApiError::OffsetBeyondEnd {
+            offset: start_pos,
+            size: existing.size,
+        });
+    }
+
+    debug!(
+        "Continuing upload of '{}' after {} existing bytes",
+        existing_path.display(),
+        start_pos
+    );
+
+    let prefix = download_file(api_config, token, existing_path, 0).await?;
+    let combined = prefix.take(start_pos).chain(bytes);
+
+    upload_file(api_config, token, parent_path, new_name, name, combined).await
+}
+
+/// Download the file at `path`, starting at byte `start_pos`.
+///
+/// A Range request is sent to the FileHandlerService for offsets other than 0.
+/// If it ignores the header and answers with the full file the leading bytes are skipped.
+/// An offset at the exact end of the file yields an empty download.
 pub async fn download_file(
     api_config: &ApiConfig,
     token: &str,
     path: &Path,
+    start_pos: u64,
 ) -> Result<Box<dyn AsyncRead + Send + Sync + Unpin>> {
     // inspired by https://github.com/benkay86/async-applied/blob/master/reqwest-tokio-compat/src/main.rs
     let url = format!(
@@ -262,14 +330,50 @@ pub async fn download_file(
     let params = [("token", token)];
     let url = reqwest::Url::parse_with_params(&url, &params).unwrap();
 
+    let mut request = api_config.client.get(url);
+    if start_pos != 0 {
+        request = request.header(RANGE, format!("bytes={}-", start_pos));
+    }
+
+    let response = send_idempotent(api_config, request, "fhs:/download").await?;
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // the range starts at or after the end, only the exact end is a valid resume point
+        let size = get_inode(api_config, path, token).await?.size;
+        if size == start_pos {
+            return Ok(Box::new(tokio::io::empty()));
+        }
+        return Err(ApiError::OffsetBeyondEnd {
+            offset: start_pos,
+            size,
+        });
+    }
+    if !response.status().is_success() {
+        return Err(error_from_response(response).await);
+    }
+    let already_at_offset = response.status() == StatusCode::PARTIAL_CONTENT;
+
     // get the content as stream and map the error so tokio can use `from` on it
-    let download = reqwest::get(url).await?.error_for_status()?;
-    let download = download
-        .bytes_stream()
-        .map_err(|e| futures::io::Error::new(futures::io::ErrorKind::Other, e));
+    let download = response.bytes_stream().map_err(futures::io::Error::other);
 
     // build a stream reader which allows us to use async read on a stream.
-    let stream_reader = StreamReader::new(download);
+    let mut stream_reader = StreamReader::new(download);
+
+    if start_pos != 0 && !already_at_offset {
+        debug!("Range was ignored, skipping the first {} bytes", start_pos);
+        let skipped = tokio::io::copy(&mut (&mut stream_reader).take(start_pos), &mut sink())
+            .await
+            .map_err(|err| {
+                ApiError::ResponseMalformed(format!("Could not skip to offset: {}", err))
+            })?;
+
+        if skipped != start_pos {
+            return Err(ApiError::OffsetBeyondEnd {
+                offset: start_pos,
+                size: skipped,
+            });
+        }
+    }
+
     Ok(Box::new(stream_reader))
 }
 
@@ -288,16 +392,129 @@ pub async fn set_last_modified_of_inode(
         timestamp: last_modified,
     };
 
-    let response = reqwest::Client::new()
+    let request = api_config
+        .client
         .put(url)
+        .timeout(api_config.request_timeout)
         .bearer_auth(token)
-        .json(&body)
-        .send()
-        .await?;
+        .json(&body);
+    let response = send_idempotent(api_config, request, "fss:/filesystem/timestamp").await?;
 
     transform_response(response, StatusCode::OK).await
 }
 
+pub async fn check_filesystemservice_health(api_config: &ApiConfig) -> Result<()> {
+    let url = format!("{}/health", api_config.fss_base_url);
+    check_health(api_config, url, "fss:/health").await
+}
+
+pub async fn check_filehandlerservice_health(api_config: &ApiConfig) -> Result<()> {
+    let url = format!("{}/health", api_config.fhs_base_url);
+    check_health(api_config, url, "fhs:/health").await
+}
+
+/// Check that a backend is reachable and its health endpoint answers without a server error
+async fn check_health(api_config: &ApiConfig, url: String, endpoint: &'static str) -> Result<()> {
+    let request = api_config
+        .client
+        .get(url)
+        .timeout(api_config.request_timeout);
+    // probes must neither be blocked by an open circuit breaker nor close it
+    let response = send_measured(request, endpoint).await?;
+
+    if response.status().is_server_error() {
+        Err(error_from_response(response).await)
+    } else {
+        Ok(())
+    }
+}
+
+/// Send the request through the circuit breaker of the backend
+///
+/// Fails without sending anything while the circuit breaker is open.
+async fn send(
+    api_config: &ApiConfig,
+    request: RequestBuilder,
+    endpoint: &'static str,
+) -> Result<Response> {
+    let circuit_breaker = api_config.circuit_breaker(endpoint);
+    circuit_breaker.check()?;
+
+    let response = send_measured(request, endpoint).await;
+    match &response {
+        Ok(response) if response.status().is_server_error() => circuit_breaker.record_failure(),
+        Err(_) => circuit_breaker.record_failure(),
+        Ok(_) => circuit_breaker.record_success(),
+    }
+
+    Ok(response?)
+}
+
+/// Send the request and record its latency and failures for the endpoint
+async fn send_measured(
+    request: RequestBuilder,
+    endpoint: &'static str,
+) -> reqwest::Result<Response> {
+    let timer = BACKEND_REQUEST_DURATION
+        .with_label_values(&[endpoint])
+        .start_timer();
+    let response = request.send().await;
+    timer.observe_duration();
+
+    match &response {
+        Ok(response) if !response.status().is_success() => BACKEND_ERRORS
+            .with_label_values(&[endpoint, response.status().as_str()])
+            .inc(),
+        Err(_) => BACKEND_ERRORS
+            .with_label_values(&[endpoint, "request"])
+            .inc(),
+        Ok(_) => {}
+    }
+    response
+}
+
+/// Like [`send`], but repeats the request after connection errors, timeouts and overloaded
+/// backends as configured by the [`RetryPolicy`]. Only for requests without side effects
+/// or with the same effect when sent twice.
+async fn send_idempotent(
+    api_config: &ApiConfig,
+    request: RequestBuilder,
+    endpoint: &'static str,
+) -> Result<Response> {
+    let policy = &api_config.retry;
+    let mut attempts = 0;
+
+    loop {
+        // streaming bodies can't be cloned, they are sent once
+        let Some(attempt) = request.try_clone() else {
+            return send(api_config, request, endpoint).await;
+        };
+        let result = send(api_config, attempt, endpoint).await;
+        attempts += 1;
+        if attempts >= policy.max_attempts {
+            return result;
+        }
+
+        let requested_delay = match &result {
+            Ok(response) if RetryPolicy::is_retryable(response.status()) => {
+                retry_after(response.headers())
+            }
+            Err(ApiError::ReqwestError(err)) if err.is_connect() || err.is_timeout() => None,
+            _ => return result,
+        };
+        let Some(delay) = policy.delay(attempts, requested_delay) else {
+            return result;
+        };
+
+        debug!(
+            "Retrying {} in {:?} after attempt {} failed",
+            endpoint, delay, attempts
+        );
+        BACKEND_RETRIES.with_label_values(&[endpoint]).inc();
+        tokio::time::sleep(delay).await;
+    }
+}
+
 async fn transform_response<T>(response: Response, expected_status: StatusCode) -> Result<T>
 where
     T: DeserializeOwned,
@@ -305,13 +522,24 @@ where
     if expected_status == response.status() {
         Ok(response.json().await?)
     } else {
-        let error_response = response.json::<ErrorResponse>().await?;
-        Err(ApiError::ResponseMalformed(format!(
-            "Error response with code '{}' and reason '{}'.",
-            error_response
-                .status
-                .unwrap_or_else(|| "unkown".to_string()),
-            error_response.message
-        )))
+        Err(error_from_response(response).await)
     }
 }
+
+/// Build a typed error from a response with an unexpected status code.
+///
+/// The body is parsed as [`ErrorResponse`] if possible, proxies in front of the backends
+/// might answer with something else.
+async fn error_from_response(response: Response) -> ApiError {
+    let status = response.status();
+    let message = match response.json::<ErrorResponse>().await {
+        Ok(error_response) => error_response.message,
+        Err(_) => status.canonical_reason().unwrap_or("unknown").to_owned(),
+    };
+
+    debug!(
+        "Error response with code '{}' and reason '{}'.",
+        status, message
+    );
+    ApiError::from_status(status, message)
+}
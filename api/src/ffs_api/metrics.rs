@@ -0,0 +1,32 @@
+use prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
+use std::sync::LazyLock;
+
+/// Latency until the response headers of the FileSystemService or FileHandlerService arrived
+pub static BACKEND_REQUEST_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "ftp_fighter_backend_request_duration_seconds",
+        "Latency of requests to the FileSystemService and FileHandlerService.",
+        &["endpoint"]
+    )
+    .unwrap()
+});
+
+/// Failed requests, `error` is the response code or `request` if no response arrived
+pub static BACKEND_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "ftp_fighter_backend_errors_total",
+        "Failed requests to the FileSystemService and FileHandlerService.",
+        &["endpoint", "error"]
+    )
+    .unwrap()
+});
+
+/// Requests that are sent again after a transient failure
+pub static BACKEND_RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "ftp_fighter_backend_retries_total",
+        "Retried requests to the FileSystemService and FileHandlerService.",
+        &["endpoint"]
+    )
+    .unwrap()
+});
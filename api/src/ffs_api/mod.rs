@@ -1,12 +1,88 @@
-use reqwest::Error;
+use reqwest::{Client, Error, StatusCode};
+use retry::{CircuitBreaker, RetryPolicy};
+use std::{sync::Arc, time::Duration};
 
 pub mod endpoints;
+pub mod metrics;
 pub mod models;
+pub mod retry;
 
+/// Connection and configuration shared by all calls to the FileSystemService and FileHandlerService.
+///
+/// Cloning is cheap, all clones use the same connection pool.
 #[derive(Debug, Clone)]
 pub struct ApiConfig {
     pub fss_base_url: String,
     pub fhs_base_url: String,
+    /// Timeout for a whole request that is not streaming file contents
+    pub request_timeout: Duration,
+    pub client: Client,
+    /// Used for requests that can safely be sent again
+    pub retry: RetryPolicy,
+    pub fss_circuit_breaker: Arc<CircuitBreaker>,
+    pub fhs_circuit_breaker: Arc<CircuitBreaker>,
+}
+
+/// Settings for the pooled http client used by [`ApiConfig`]
+#[derive(Debug, Clone)]
+pub struct HttpClientConfig {
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout: Duration,
+    pub tcp_keepalive: Duration,
+    pub connect_timeout: Duration,
+    pub request_timeout: Duration,
+    /// Use HTTP/2 without upgrade negotiation, useful for plain http backends
+    pub http2_prior_knowledge: bool,
+    pub retry: RetryPolicy,
+    /// Failures in a row after which a backend isn't asked anymore, 0 disables this
+    pub circuit_breaker_threshold: u32,
+    /// How long a backend isn't asked after too many failures
+    pub circuit_breaker_cooldown: Duration,
+}
+
+impl ApiConfig {
+    pub fn new(
+        fss_base_url: String,
+        fhs_base_url: String,
+        http_config: &HttpClientConfig,
+    ) -> Result<Self> {
+        let mut builder = Client::builder()
+            .pool_max_idle_per_host(http_config.pool_max_idle_per_host)
+            .pool_idle_timeout(http_config.pool_idle_timeout)
+            .tcp_keepalive(http_config.tcp_keepalive)
+            .connect_timeout(http_config.connect_timeout);
+
+        if http_config.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+
+        Ok(Self {
+            fss_base_url,
+            fhs_base_url,
+            request_timeout: http_config.request_timeout,
+            client: builder.build()?,
+            retry: http_config.retry.clone(),
+            fss_circuit_breaker: Arc::new(CircuitBreaker::new(
+                "FileSystemService",
+                http_config.circuit_breaker_threshold,
+                http_config.circuit_breaker_cooldown,
+            )),
+            fhs_circuit_breaker: Arc::new(CircuitBreaker::new(
+                "FileHandlerService",
+                http_config.circuit_breaker_threshold,
+                http_config.circuit_breaker_cooldown,
+            )),
+        })
+    }
+
+    /// The circuit breaker of the backend an endpoint label like `fhs:/upload` belongs to
+    pub fn circuit_breaker(&self, endpoint: &str) -> &CircuitBreaker {
+        if endpoint.starts_with("fhs:") {
+            &self.fhs_circuit_breaker
+        } else {
+            &self.fss_circuit_breaker
+        }
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -15,6 +91,46 @@ pub enum ApiError {
     ReqwestError(reqwest::Error),
     #[error("Response was malformed: {0}")]
     ResponseMalformed(String),
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+    #[error("Offset {offset} is beyond the end of the file ({size} bytes)")]
+    OffsetBeyondEnd { offset: u64, size: u64 },
+    #[error("Circuit open: {0}")]
+    CircuitOpen(String),
+    #[error("Unexpected response code {0}: {1}")]
+    UnexpectedStatus(StatusCode, String),
+}
+
+impl ApiError {
+    /// Classify an error response of the backends by its status code
+    pub fn from_status(status: StatusCode, message: String) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => Self::BadRequest(message),
+            StatusCode::UNAUTHORIZED => Self::Unauthorized(message),
+            StatusCode::FORBIDDEN => Self::Forbidden(message),
+            StatusCode::NOT_FOUND | StatusCode::GONE => Self::NotFound(message),
+            StatusCode::CONFLICT => Self::Conflict(message),
+            StatusCode::PAYLOAD_TOO_LARGE | StatusCode::INSUFFICIENT_STORAGE => {
+                Self::PayloadTooLarge(message)
+            }
+            StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => Self::ServiceUnavailable(message),
+            status => Self::UnexpectedStatus(status, message),
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, ApiError>;
@@ -1,6 +1,6 @@
 use super::user_resource::UserResource;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InodeResource {
     #[serde(rename = "id")]
     pub id: String,
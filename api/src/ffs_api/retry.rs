@@ -0,0 +1,134 @@
+use super::{ApiError, Result};
+use rand::Rng;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    StatusCode,
+};
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// How often and how fast idempotent requests are repeated after transient failures
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Including the first attempt, 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every further one
+    pub base_delay: Duration,
+    /// Upper bound of the delay, also for `Retry-After`
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Responses worth another attempt, the backend or a proxy in front of it is overloaded
+    pub fn is_retryable(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+
+    /// Delay before the given retry (starting at 1), `None` if the backend asked for more
+    /// than `max_delay`.
+    ///
+    /// Without `Retry-After` the delay grows exponentially, half of it is random so that
+    /// sessions failing at the same time don't retry at the same time.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if let Some(retry_after) = retry_after {
+            return (retry_after <= self.max_delay).then_some(retry_after);
+        }
+
+        let exponential = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        let half = exponential / 2;
+        Some(half + half.mul_f64(rand::thread_rng().gen::<f64>()))
+    }
+}
+
+/// Seconds of a `Retry-After` header, dates are not supported
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Stops sending requests to a backend that keeps failing.
+///
+/// After `threshold` failures in a row every request fails immediately until `cooldown`
+/// passed. Then requests are let through again, the first success closes the breaker and
+/// the next failure opens it for another `cooldown`.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    backend: &'static str,
+    /// 0 disables the breaker
+    threshold: u32,
+    cooldown: Duration,
+    failures: AtomicU32,
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(backend: &'static str, threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            backend,
+            threshold,
+            cooldown,
+            failures: AtomicU32::new(0),
+            open_until: Mutex::new(None),
+        }
+    }
+
+    /// Fail fast while the breaker is open
+    pub fn check(&self) -> Result<()> {
+        let open_until = *self
+            .open_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match open_until {
+            Some(open_until) if Instant::now() < open_until => Err(ApiError::CircuitOpen(format!(
+                "{} is unavailable, not sending requests",
+                self.backend
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        if self.failures.swap(0, Ordering::Relaxed) >= self.threshold && self.threshold != 0 {
+            info!("{} is available again", self.backend);
+        }
+        *self
+            .open_until
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    pub fn record_failure(&self) {
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.threshold != 0 && failures >= self.threshold {
+            warn!(
+                "{} failed {} times in a row, pausing requests for {:?}",
+                self.backend, failures, self.cooldown
+            );
+            *self
+                .open_until
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner) = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
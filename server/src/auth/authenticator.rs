@@ -1,25 +1,72 @@
-use super::user::FileFighterUser;
+use super::{
+    login_cache::{CachedLogin, LoginCache},
+    privileges::Privileges,
+    user::FileFighterUser,
+};
+use crate::{metrics::LOGINS, shutdown::GracefulShutdown};
 use async_trait::async_trait;
 use filefighter_api::ffs_api::{
-    endpoints::{get_token_for_user, get_user_info},
-    ApiConfig,
+    endpoints::{get_token_for_password_hash, get_user_info, hash_password},
+    ApiConfig, ApiError,
 };
-use libunftp::auth::{AuthenticationError, Authenticator, Credentials};
+use libunftp::auth::{AuthenticationError, Authenticator, Credentials, UserDetail};
+use std::sync::Arc;
 use tracing::{debug, instrument, warn};
 
 #[derive(Debug)]
 pub struct FileFighterAuthenticator {
     pub api_config: ApiConfig,
+    /// Shared with the storage backends, `None` if caching is disabled
+    pub login_cache: Option<Arc<LoginCache>>,
+    /// New logins are refused once the server is shutting down
+    pub shutdown: Arc<GracefulShutdown>,
 }
 
-#[async_trait]
-impl Authenticator<FileFighterUser> for FileFighterAuthenticator {
-    #[instrument(skip(self, creds), level = "debug")]
-    async fn authenticate(
+impl FileFighterAuthenticator {
+    /// Get a new token and the user details from the `FileSystemService`
+    async fn login(
+        &self,
+        username: &str,
+        password_hash: &str,
+    ) -> Result<CachedLogin, AuthenticationError> {
+        let token = get_token_for_password_hash(&self.api_config, username, password_hash)
+            .await
+            .map_err(|err| {
+                warn!("Cought Error: {}", err);
+                if matches!(err, ApiError::Unauthorized(_) | ApiError::Forbidden(_)) {
+                    AuthenticationError::BadPassword
+                } else {
+                    AuthenticationError::new(err.to_string())
+                }
+            })?;
+
+        debug!("Got token {}", token);
+
+        let user_ressource = get_user_info(&self.api_config, &token)
+            .await
+            .map_err(|err| {
+                warn!("Cought Error: {}", err);
+                AuthenticationError::BadUser
+            })?;
+
+        debug!("Got user {:?}", user_ressource);
+
+        Ok(CachedLogin {
+            token,
+            user: Arc::new(user_ressource),
+        })
+    }
+
+    /// Check the credentials, reusing a cached login if possible
+    async fn authenticate_user(
         &self,
         username: &str,
         creds: &Credentials,
     ) -> Result<FileFighterUser, AuthenticationError> {
+        if self.shutdown.is_shutting_down() {
+            return Err(AuthenticationError::new("Server is shutting down"));
+        }
+
         if username.is_empty() {
             return Err(AuthenticationError::BadUser);
         }
@@ -33,29 +80,58 @@ impl Authenticator<FileFighterUser> for FileFighterAuthenticator {
             return Err(AuthenticationError::BadPassword);
         }
 
-        // IDEA: the lib does cache the user?
-        let token = get_token_for_user(&self.api_config, username, password)
-            .await
-            .map_err(|err| {
-                warn!("Cought Error: {}", err);
-                AuthenticationError::new(err.to_string())
-            })?;
+        let password_hash = hash_password(password);
+        let cached = self
+            .login_cache
+            .as_ref()
+            .map(|cache| (cache, cache.key(username, password)));
 
-        debug!("Got token {}", token);
+        let login = if let Some(login) = cached.as_ref().and_then(|(cache, key)| cache.get(key)) {
+            debug!("Reusing cached login");
+            login
+        } else {
+            let login = self.login(username, &password_hash).await?;
+            if let Some((cache, key)) = cached {
+                cache.insert(key, login.clone());
+            }
+            login
+        };
 
-        let user_ressource = get_user_info(&self.api_config, &token)
-            .await
-            .map_err(|err| {
-                warn!("Cought Error: {}", err);
-                AuthenticationError::BadUser
-            })?;
+        let privileges = Privileges::parse(&login.user.privileges);
+        if !privileges.can_login() {
+            warn!(
+                "Refusing login of {} with privileges {:?}",
+                username, privileges
+            );
+        }
 
-        debug!("Got user {:?}", user_ressource);
+        Ok(FileFighterUser::new(
+            login.user.id,
+            username.to_owned(),
+            privileges,
+            login.token,
+            password_hash,
+        ))
+    }
+}
 
-        Ok(FileFighterUser {
-            username: username.to_owned(),
-            token,
-            id: user_ressource.id,
-        })
+#[async_trait]
+impl Authenticator<FileFighterUser> for FileFighterAuthenticator {
+    #[instrument(skip(self, creds), level = "debug")]
+    async fn authenticate(
+        &self,
+        username: &str,
+        creds: &Credentials,
+    ) -> Result<FileFighterUser, AuthenticationError> {
+        let result = self.authenticate_user(username, creds).await;
+
+        let outcome = match &result {
+            Ok(user) if user.account_enabled() => "success",
+            Ok(_) => "disabled",
+            Err(_) => "failure",
+        };
+        LOGINS.with_label_values(&[outcome]).inc();
+
+        result
     }
 }
@@ -0,0 +1,91 @@
+use filefighter_api::ffs_api::models::user_resource::UserResource;
+use moka::sync::Cache;
+use std::{fmt::Debug, sync::Arc, time::Duration};
+use tracing::{debug, warn};
+
+/// A login accepted by the `FileSystemService`
+#[derive(Debug, Clone)]
+pub struct CachedLogin {
+    pub token: String,
+    pub user: Arc<UserResource>,
+}
+
+/// Recently accepted logins, so parallel sessions of a user reuse one token.
+///
+/// Entries are keyed by the username and a salted hash of the password, the salt is
+/// generated per process so the keys can't be reused elsewhere.
+pub struct LoginCache {
+    logins: Cache<String, CachedLogin>,
+    salt: [u8; 16],
+}
+
+impl Debug for LoginCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoginCache")
+            .field("entries", &self.logins.entry_count())
+            .finish_non_exhaustive()
+    }
+}
+
+impl LoginCache {
+    #[must_use]
+    pub fn new(capacity: u64, time_to_live: Duration) -> Self {
+        let mut salt = [0_u8; 16];
+        if let Err(err) = getrandom::getrandom(&mut salt) {
+            // still unique per user and password, just not unpredictable
+            warn!("Could not generate salt for the login cache: {}", err);
+        }
+
+        Self {
+            logins: Cache::builder()
+                .max_capacity(capacity)
+                .time_to_live(time_to_live)
+                .support_invalidation_closures()
+                .build(),
+            salt,
+        }
+    }
+
+    #[must_use]
+    pub fn key(&self, username: &str, password: &str) -> String {
+        let mut salted = self.salt.to_vec();
+        salted.extend_from_slice(password.as_bytes());
+        format!("{}:{}", username, sha256::digest(salted.as_slice()))
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<CachedLogin> {
+        self.logins.get(key)
+    }
+
+    pub fn insert(&self, key: String, login: CachedLogin) {
+        self.logins.insert(key, login);
+    }
+
+    /// Let every login that used the old token continue with the renewed one
+    pub fn replace_token(&self, old_token: &str, new_token: &str) {
+        for (key, login) in &self.logins {
+            if login.token == old_token {
+                self.logins.insert(
+                    (*key).clone(),
+                    CachedLogin {
+                        token: new_token.to_owned(),
+                        user: login.user,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Forget every login that uses this token, e.g. because the backend rejected it
+    pub fn invalidate_token(&self, token: &str) {
+        debug!("Invalidating cached logins of a rejected token");
+        let token = token.to_owned();
+        if let Err(err) = self
+            .logins
+            .invalidate_entries_if(move |_, login| login.token == token)
+        {
+            warn!("Could not invalidate cached logins: {}", err);
+        }
+    }
+}
@@ -1,2 +1,6 @@
 pub mod authenticator;
+pub mod login_cache;
+pub mod privileges;
+#[cfg(test)]
+pub mod privileges_test;
 pub mod user;
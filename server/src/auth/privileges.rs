@@ -0,0 +1,39 @@
+/// Privileges of a `FileFighter` user relevant for FTP access.
+///
+/// Parsed from the `privileges` of the `UserResource`, which is a list of names separated by
+/// commas or whitespace. Unknown names are ignored, so a user without any of these is a regular
+/// user with read and write access.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Privileges {
+    /// `DISABLED`: the account can't log in
+    pub disabled: bool,
+    /// `LOCKED`: the account can't log in
+    pub locked: bool,
+    /// `READ_ONLY`: the account may only list and download
+    pub read_only: bool,
+}
+
+impl Privileges {
+    pub fn parse(privileges: &str) -> Self {
+        privileges
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .map(|name| name.trim().to_uppercase().replace('-', "_"))
+            .fold(Self::default(), |mut parsed, name| {
+                match name.as_str() {
+                    "DISABLED" => parsed.disabled = true,
+                    "LOCKED" => parsed.locked = true,
+                    "READ_ONLY" | "READONLY" => parsed.read_only = true,
+                    _ => {}
+                }
+                parsed
+            })
+    }
+
+    pub const fn can_login(self) -> bool {
+        !self.disabled && !self.locked
+    }
+
+    pub const fn can_write(self) -> bool {
+        !self.read_only
+    }
+}
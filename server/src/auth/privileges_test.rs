@@ -0,0 +1,26 @@
+#[cfg(test)]
+mod privileges_parse_tests {
+    use crate::auth::privileges::Privileges;
+
+    #[test]
+    fn regular_users_have_full_access() {
+        let privileges = Privileges::parse("NORMAL");
+        assert!(privileges.can_login());
+        assert!(privileges.can_write());
+        assert_eq!(Privileges::parse(""), Privileges::default());
+    }
+
+    #[test]
+    fn names_are_parsed_from_lists() {
+        let privileges = Privileges::parse("normal, read-only");
+        assert!(privileges.can_login());
+        assert!(!privileges.can_write());
+
+        let privileges = Privileges::parse("ADMIN LOCKED");
+        assert!(privileges.locked);
+        assert!(!privileges.can_login());
+
+        assert!(!Privileges::parse("DISABLED").can_login());
+        assert!(!Privileges::parse("READONLY").can_write());
+    }
+}
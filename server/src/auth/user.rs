@@ -1,10 +1,51 @@
+use super::privileges::Privileges;
 use libunftp::auth::UserDetail;
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    sync::{PoisonError, RwLock},
+};
 
 pub struct FileFighterUser {
     pub id: u32,
     pub username: String,
-    pub token: String,
+    pub privileges: Privileges,
+    token: RwLock<String>,
+    /// Kept for the session to renew the token, never logged or sent anywhere else
+    password_hash: String,
+}
+
+impl FileFighterUser {
+    pub const fn new(
+        id: u32,
+        username: String,
+        privileges: Privileges,
+        token: String,
+        password_hash: String,
+    ) -> Self {
+        Self {
+            id,
+            username,
+            privileges,
+            token: RwLock::new(token),
+            password_hash,
+        }
+    }
+
+    /// The current bearer token of the session
+    pub fn token(&self) -> String {
+        self.token
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn set_token(&self, token: String) {
+        *self.token.write().unwrap_or_else(PoisonError::into_inner) = token;
+    }
+
+    pub fn password_hash(&self) -> &str {
+        &self.password_hash
+    }
 }
 
 impl Display for FileFighterUser {
@@ -17,12 +58,12 @@ impl Debug for FileFighterUser {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("FileFighterUser")
             .field("username", &self.username)
-            .finish()
+            .finish_non_exhaustive()
     }
 }
 
 impl UserDetail for FileFighterUser {
     fn account_enabled(&self) -> bool {
-        true
+        self.privileges.can_login()
     }
 }
@@ -0,0 +1,208 @@
+use md5::{Digest, Md5};
+use moka::sync::Cache;
+use sha2::Sha256;
+use std::{
+    fmt::Debug,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+use tracing::warn;
+
+/// Hash algorithms checksums of stored files can be computed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    /// Name of the algorithm for log messages
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Sha256 => "SHA-256",
+        }
+    }
+
+    /// Hash everything the reader returns, as lower case hex
+    ///
+    /// # Errors
+    /// Fails if reading fails
+    pub async fn digest<R: AsyncRead + Unpin + Send>(
+        self,
+        mut reader: R,
+    ) -> std::io::Result<String> {
+        let mut hasher = Hasher::new(self);
+        let mut buffer = vec![0_u8; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            match buffer.get(..read) {
+                Some([]) | None => break,
+                Some(bytes) => hasher.update(bytes),
+            }
+        }
+        Ok(hasher.finalize())
+    }
+}
+
+enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Md5 => Self::Md5(Md5::new()),
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Md5(hasher) => hasher.update(bytes),
+            Self::Sha256(hasher) => hasher.update(bytes),
+        }
+    }
+
+    fn finalize(self) -> String {
+        match self {
+            Self::Md5(hasher) => format!("{:x}", hasher.finalize()),
+            Self::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
+/// Identifies the contents of a file: a changed file gets a new `last_updated`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ChecksumKey {
+    inode_id: String,
+    last_updated: u64,
+    algorithm: HashAlgorithm,
+}
+
+/// Checksums computed before, so repeated requests don't download the file again
+pub struct ChecksumCache {
+    checksums: Cache<ChecksumKey, String>,
+}
+
+impl Debug for ChecksumCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChecksumCache")
+            .field("entries", &self.checksums.entry_count())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ChecksumCache {
+    #[must_use]
+    pub fn new(capacity: u64) -> Self {
+        Self {
+            checksums: Cache::builder()
+                .max_capacity(capacity)
+                .support_invalidation_closures()
+                .build(),
+        }
+    }
+
+    #[must_use]
+    pub fn get(
+        &self,
+        inode_id: &str,
+        last_updated: u64,
+        algorithm: HashAlgorithm,
+    ) -> Option<String> {
+        self.checksums.get(&ChecksumKey {
+            inode_id: inode_id.to_owned(),
+            last_updated,
+            algorithm,
+        })
+    }
+
+    pub fn insert(
+        &self,
+        inode_id: &str,
+        last_updated: u64,
+        algorithm: HashAlgorithm,
+        checksum: String,
+    ) {
+        self.checksums.insert(
+            ChecksumKey {
+                inode_id: inode_id.to_owned(),
+                last_updated,
+                algorithm,
+            },
+            checksum,
+        );
+    }
+
+    /// Forget the checksums of every version of the inode
+    ///
+    /// `last_updated` has a resolution of seconds, so a file overwritten within the same
+    /// second keeps its key.
+    pub fn forget(&self, inode_id: &str) {
+        let inode_id = inode_id.to_owned();
+        if let Err(err) = self
+            .checksums
+            .invalidate_entries_if(move |key, _| key.inode_id == inode_id)
+        {
+            warn!("Could not invalidate cached checksums: {}", err);
+        }
+    }
+}
+
+/// Size and SHA-256 of the bytes read through a [`DigestingReader`]
+#[derive(Debug, Default, Clone)]
+pub struct UploadDigest {
+    pub size: u64,
+    sha256: Sha256,
+}
+
+impl UploadDigest {
+    /// The SHA-256 of everything read so far, as lower case hex
+    #[must_use]
+    pub fn sha256(&self) -> String {
+        format!("{:x}", self.sha256.clone().finalize())
+    }
+}
+
+/// Passes the bytes of an upload through while recording their size and hash
+pub struct DigestingReader<R> {
+    inner: R,
+    digest: Arc<Mutex<UploadDigest>>,
+}
+
+impl<R> DigestingReader<R> {
+    /// The digest stays readable through the returned handle after the reader was consumed
+    pub fn new(inner: R) -> (Self, Arc<Mutex<UploadDigest>>) {
+        let digest = Arc::new(Mutex::new(UploadDigest::default()));
+        (
+            Self {
+                inner,
+                digest: Arc::clone(&digest),
+            },
+            digest,
+        )
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for DigestingReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        if let Some(read) = buf.filled().get(before..) {
+            let mut digest = self.digest.lock().unwrap_or_else(PoisonError::into_inner);
+            digest.size += read.len() as u64;
+            digest.sha256.update(read);
+        }
+        result
+    }
+}
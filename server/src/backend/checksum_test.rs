@@ -0,0 +1,81 @@
+// tests may panic
+#![allow(clippy::unwrap_used)]
+
+#[cfg(test)]
+mod hash_algorithm_tests {
+    use crate::backend::checksum::HashAlgorithm;
+
+    async fn hash(algorithm: HashAlgorithm, contents: &[u8]) -> String {
+        algorithm.digest(contents).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn known_checksums_are_computed() {
+        let contents = b"The quick brown fox jumps over the lazy dog";
+        assert_eq!(
+            hash(HashAlgorithm::Md5, contents).await,
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+        assert_eq!(
+            hash(HashAlgorithm::Sha256, contents).await,
+            "d7a8fbb307d7809469ca9abcb0082e4f8d5651e46d3cdb762d02d0bf37c9e592"
+        );
+    }
+}
+
+#[cfg(test)]
+mod checksum_cache_tests {
+    use crate::backend::checksum::{ChecksumCache, HashAlgorithm};
+
+    #[test]
+    fn checksums_are_cached_per_version_and_algorithm() {
+        let cache = ChecksumCache::new(10);
+        cache.insert("1", 100, HashAlgorithm::Md5, "abc".to_owned());
+
+        assert_eq!(
+            cache.get("1", 100, HashAlgorithm::Md5).as_deref(),
+            Some("abc")
+        );
+        assert!(cache.get("1", 101, HashAlgorithm::Md5).is_none());
+        assert!(cache.get("1", 100, HashAlgorithm::Sha256).is_none());
+        assert!(cache.get("2", 100, HashAlgorithm::Md5).is_none());
+    }
+
+    #[test]
+    fn forgotten_inodes_lose_all_checksums() {
+        let cache = ChecksumCache::new(10);
+        cache.insert("1", 100, HashAlgorithm::Md5, "abc".to_owned());
+        cache.insert("1", 100, HashAlgorithm::Sha256, "def".to_owned());
+        cache.insert("2", 100, HashAlgorithm::Md5, "ghi".to_owned());
+
+        cache.forget("1");
+
+        assert!(cache.get("1", 100, HashAlgorithm::Md5).is_none());
+        assert!(cache.get("1", 100, HashAlgorithm::Sha256).is_none());
+        assert_eq!(
+            cache.get("2", 100, HashAlgorithm::Md5).as_deref(),
+            Some("ghi")
+        );
+    }
+}
+
+#[cfg(test)]
+mod digesting_reader_tests {
+    use crate::backend::checksum::DigestingReader;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn size_and_hash_of_read_bytes_are_recorded() {
+        let (mut reader, digest) = DigestingReader::new(&b"hello ftp"[..]);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).await.unwrap();
+
+        let digest = digest.lock().unwrap().clone();
+        assert_eq!(read, b"hello ftp");
+        assert_eq!(digest.size, 9);
+        assert_eq!(
+            digest.sha256(),
+            "d36bc1618be86a58018b350632c25057abcea2602668a549ab1328abad09bd63"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use filefighter_api::ffs_api::models::{
+    contents_resource::ContentsResource, inode_resource::InodeResource,
+};
+use moka::sync::Cache;
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ListingKey {
+    user_id: u32,
+    path: PathBuf,
+}
+
+impl ListingKey {
+    fn new(user_id: u32, path: &Path) -> Self {
+        Self {
+            user_id,
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+/// Folder contents and inodes fetched in the last few seconds, per user and normalized path.
+///
+/// Clients like to send `CWD`, `SIZE`, `MDTM` and `LIST` in quick succession, which would
+/// otherwise ask the `FileSystemService` the same questions over and over. Writes through
+/// this server invalidate the affected paths for every user, changes made elsewhere show up
+/// once the entries expire.
+pub struct ListingCache {
+    contents: Cache<ListingKey, Arc<ContentsResource>>,
+    inodes: Cache<ListingKey, Arc<InodeResource>>,
+}
+
+impl Debug for ListingCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ListingCache")
+            .field("contents", &self.contents.entry_count())
+            .field("inodes", &self.inodes.entry_count())
+            .finish()
+    }
+}
+
+impl ListingCache {
+    #[must_use]
+    pub fn new(capacity: u64, time_to_live: Duration) -> Self {
+        Self {
+            contents: Self::build(capacity, time_to_live),
+            inodes: Self::build(capacity, time_to_live),
+        }
+    }
+
+    fn build<V>(capacity: u64, time_to_live: Duration) -> Cache<ListingKey, V>
+    where
+        V: Clone + Send + Sync + 'static,
+    {
+        Cache::builder()
+            .max_capacity(capacity)
+            .time_to_live(time_to_live)
+            .support_invalidation_closures()
+            .build()
+    }
+
+    #[must_use]
+    pub fn contents(&self, user_id: u32, path: &Path) -> Option<Arc<ContentsResource>> {
+        self.contents.get(&ListingKey::new(user_id, path))
+    }
+
+    pub fn insert_contents(&self, user_id: u32, path: &Path, contents: Arc<ContentsResource>) {
+        self.contents
+            .insert(ListingKey::new(user_id, path), contents);
+    }
+
+    /// A cached inode, or the matching entry of a cached listing of its parent
+    #[must_use]
+    pub fn inode(&self, user_id: u32, path: &Path) -> Option<Arc<InodeResource>> {
+        if let Some(inode) = self.inodes.get(&ListingKey::new(user_id, path)) {
+            return Some(inode);
+        }
+
+        let parent = self.contents(user_id, path.parent()?)?;
+        let name = path.file_name()?.to_str()?;
+        let inode = parent.inodes.iter().find(|inode| inode.name == name)?;
+        debug!(
+            "Found {} in the cached listing of its parent",
+            path.display()
+        );
+        Some(Arc::new(inode.clone()))
+    }
+
+    pub fn insert_inode(&self, user_id: u32, path: &Path, inode: Arc<InodeResource>) {
+        self.inodes.insert(ListingKey::new(user_id, path), inode);
+    }
+
+    /// Forget the path, everything below it and its parent (whose listing changed), for all users
+    pub fn invalidate(&self, path: &Path) {
+        let parent = path.parent().map(Path::to_path_buf);
+        let path = path.to_path_buf();
+        let affected = move |key: &ListingKey| {
+            key.path.starts_with(&path) || parent.as_ref() == Some(&key.path)
+        };
+
+        let affected_contents = affected.clone();
+        if let Err(err) = self
+            .contents
+            .invalidate_entries_if(move |key, _| affected_contents(key))
+        {
+            warn!("Could not invalidate cached listings: {}", err);
+        }
+        if let Err(err) = self
+            .inodes
+            .invalidate_entries_if(move |key, _| affected(key))
+        {
+            warn!("Could not invalidate cached inodes: {}", err);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+// tests may panic
+#![allow(clippy::unwrap_used)]
+
+#[cfg(test)]
+mod listing_cache_tests {
+    use crate::backend::listing_cache::ListingCache;
+    use filefighter_api::ffs_api::models::{
+        contents_resource::ContentsResource, inode_resource::InodeResource,
+        user_resource::UserResource,
+    };
+    use std::{path::Path, sync::Arc, time::Duration};
+
+    fn user() -> UserResource {
+        UserResource {
+            id: 1,
+            privileges: "NORMAL".to_owned(),
+            username: "user".to_owned(),
+        }
+    }
+
+    fn inode(path: &str) -> InodeResource {
+        InodeResource {
+            id: path.to_owned(),
+            last_updated: 1_600_000_000,
+            last_updated_by: user(),
+            mime_type: Some("text/plain".to_owned()),
+            name: Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned(),
+            path: path.to_owned(),
+            size: 5,
+        }
+    }
+
+    fn cache_with_home() -> ListingCache {
+        let cache = ListingCache::new(100, Duration::from_secs(30));
+        cache.insert_contents(
+            1,
+            Path::new("/Home"),
+            Arc::new(ContentsResource {
+                inodes: vec![inode("/Home/notes.txt")],
+                owner: user(),
+            }),
+        );
+        cache
+    }
+
+    #[test]
+    fn inodes_are_found_in_the_listing_of_their_parent() {
+        let cache = cache_with_home();
+
+        let inode = cache.inode(1, Path::new("/Home/notes.txt")).unwrap();
+
+        assert_eq!(inode.path, "/Home/notes.txt");
+        assert!(cache.inode(1, Path::new("/Home/other.txt")).is_none());
+    }
+
+    #[test]
+    fn entries_are_kept_per_user() {
+        let cache = cache_with_home();
+
+        assert!(cache.contents(2, Path::new("/Home")).is_none());
+        assert!(cache.inode(2, Path::new("/Home/notes.txt")).is_none());
+    }
+
+    #[test]
+    fn invalidation_covers_the_subtree_and_the_parent() {
+        let cache = cache_with_home();
+        cache.insert_inode(1, Path::new("/Home"), Arc::new(inode("/Home")));
+        cache.insert_inode(
+            1,
+            Path::new("/Home/Pictures/cat.png"),
+            Arc::new(inode("/Home/Pictures/cat.png")),
+        );
+        cache.insert_inode(1, Path::new("/Homework"), Arc::new(inode("/Homework")));
+
+        cache.invalidate(Path::new("/Home/Pictures"));
+
+        assert!(cache.contents(1, Path::new("/Home")).is_none());
+        assert!(cache
+            .inode(1, Path::new("/Home/Pictures/cat.png"))
+            .is_none());
+        assert!(cache.inode(1, Path::new("/Home")).is_none());
+        assert!(cache.inode(1, Path::new("/Homework")).is_some());
+    }
+}
@@ -1,6 +1,12 @@
+use super::utils::last_updated_to_system_time;
+use chrono::{DateTime, Utc};
 use filefighter_api::ffs_api::models::inode_resource::InodeResource;
 use libunftp::storage::{Metadata, Result};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
 
 #[derive(Debug)]
 pub struct InodeMetaData {
@@ -9,6 +15,8 @@ pub struct InodeMetaData {
     modified: SystemTime,
     gid: u32,
     uid: u32,
+    last_updated_by: String,
+    owner: String,
 }
 
 impl Metadata for InodeMetaData {
@@ -43,14 +51,75 @@ impl Metadata for InodeMetaData {
 }
 
 impl InodeMetaData {
-    pub fn from(inode: &InodeResource, owner_id: u32) -> Self {
+    pub fn from(inode: &InodeResource, owner_id: u32, owner: &str) -> Self {
         Self {
             len: inode.size,
             is_file: inode.mime_type.is_some(),
-            // TODO: does this work?
-            modified: UNIX_EPOCH + Duration::from_secs(inode.last_updated),
+            modified: last_updated_to_system_time(inode.last_updated),
             gid: owner_id,
             uid: owner_id,
+            last_updated_by: inode.last_updated_by.username.clone(),
+            owner: owner.to_owned(),
         }
     }
+
+    /// A `ls -l` style line with user names instead of the numeric ids libunftp would show
+    ///
+    /// The group is the user who last updated the inode, unless a mapping applies. Clients
+    /// split these lines at whitespace, so whitespace in the names is replaced with `_`.
+    pub fn list_line(&self, name: &str, group: Option<&str>) -> String {
+        let modified = DateTime::<Utc>::from(self.modified).format("%b %d %H:%M");
+        format!(
+            "{filetype}{permissions} {links:>12} {owner:>12} {group:>12} {size:#14} {modified:>12} {name}",
+            filetype = if self.is_dir() { "d" } else { "-" },
+            permissions = self.permissions(),
+            links = self.links(),
+            owner = list_column(&self.owner),
+            group = list_column(group.unwrap_or(&self.last_updated_by)),
+            size = self.len,
+        )
+    }
+}
+
+/// Group name shown in listings for every inode below a folder, e.g. `/Shared=team`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupMapping {
+    pub path: PathBuf,
+    pub group: String,
+}
+
+impl FromStr for GroupMapping {
+    type Err = String;
+
+    fn from_str(mapping: &str) -> std::result::Result<Self, Self::Err> {
+        match mapping.split_once('=') {
+            Some((path, group))
+                if path.starts_with('/')
+                    && !group.is_empty()
+                    && !group.contains(char::is_whitespace) =>
+            {
+                Ok(Self {
+                    path: PathBuf::from(path),
+                    group: group.to_owned(),
+                })
+            }
+            _ => Err(format!(
+                "Invalid group mapping '{mapping}', expected /path=group without whitespace"
+            )),
+        }
+    }
+}
+
+/// The group of the most specific mapping containing the path
+pub fn mapped_group<'a>(mappings: &'a [GroupMapping], path: &Path) -> Option<&'a str> {
+    mappings
+        .iter()
+        .filter(|mapping| path.starts_with(&mapping.path))
+        .max_by_key(|mapping| mapping.path.components().count())
+        .map(|mapping| mapping.group.as_str())
+}
+
+/// A name as a single column of a listing line
+fn list_column(name: &str) -> String {
+    name.replace(char::is_whitespace, "_")
 }
@@ -0,0 +1,76 @@
+// tests may panic
+#![allow(clippy::unwrap_used)]
+
+#[cfg(test)]
+mod list_line_tests {
+    use crate::backend::metadata::InodeMetaData;
+    use filefighter_api::ffs_api::models::{
+        inode_resource::InodeResource, user_resource::UserResource,
+    };
+
+    fn inode(mime_type: Option<&str>) -> InodeResource {
+        InodeResource {
+            id: "42".to_owned(),
+            last_updated: 1_600_000_000,
+            last_updated_by: UserResource {
+                id: 2,
+                privileges: "NORMAL".to_owned(),
+                username: "other user".to_owned(),
+            },
+            mime_type: mime_type.map(ToOwned::to_owned),
+            name: "notes.txt".to_owned(),
+            path: "/Home/notes.txt".to_owned(),
+            size: 5,
+        }
+    }
+
+    #[test]
+    fn list_lines_show_owner_and_last_editor() {
+        let file = InodeMetaData::from(&inode(Some("text/plain")), 1, "user");
+        let line = file.list_line("notes.txt", None);
+        assert!(line.starts_with('-'));
+        assert!(line.contains(" user "));
+        assert!(line.contains(" other_user "));
+        assert!(line.ends_with(" notes.txt"));
+
+        let folder = InodeMetaData::from(&inode(None), 1, "user");
+        let line = folder.list_line("Home", Some("team"));
+        assert!(line.starts_with('d'));
+        assert!(line.contains(" team "));
+        assert!(!line.contains("other"));
+    }
+}
+
+#[cfg(test)]
+mod group_mapping_tests {
+    use crate::backend::metadata::{mapped_group, GroupMapping};
+    use std::path::Path;
+
+    #[test]
+    fn mappings_are_parsed() {
+        let mapping: GroupMapping = "/Shared/Team A=team-a".parse().unwrap();
+        assert_eq!(mapping.path, Path::new("/Shared/Team A"));
+        assert_eq!(mapping.group, "team-a");
+
+        assert!("Shared=team".parse::<GroupMapping>().is_err());
+        assert!("/Shared=".parse::<GroupMapping>().is_err());
+        assert!("/Shared".parse::<GroupMapping>().is_err());
+        assert!("/Shared=team a".parse::<GroupMapping>().is_err());
+    }
+
+    #[test]
+    fn most_specific_mapping_wins() {
+        let mappings: Vec<GroupMapping> = ["/Shared=staff", "/Shared/Dev=dev"]
+            .iter()
+            .map(|mapping| mapping.parse().unwrap())
+            .collect();
+
+        assert_eq!(
+            mapped_group(&mappings, Path::new("/Shared/Dev/a.txt")),
+            Some("dev")
+        );
+        assert_eq!(mapped_group(&mappings, Path::new("/Shared")), Some("staff"));
+        assert_eq!(mapped_group(&mappings, Path::new("/SharedOther")), None);
+        assert_eq!(mapped_group(&mappings, Path::new("/Home")), None);
+    }
+}
@@ -1,4 +1,15 @@
+pub mod checksum;
+#[cfg(test)]
+pub mod checksum_test;
+pub mod listing_cache;
+#[cfg(test)]
+pub mod listing_cache_test;
 pub mod metadata;
+#[cfg(test)]
+pub mod metadata_test;
+pub mod quota;
+#[cfg(test)]
+pub mod quota_test;
 pub mod storage_backend;
 mod utils;
 #[cfg(test)]
@@ -0,0 +1,73 @@
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Storage quota of a user, the limit is configured for all users
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    /// Sum of the sizes the `FileSystemService` reports for the user's root folder
+    pub used: u64,
+    pub limit: u64,
+}
+
+impl Quota {
+    /// Bytes an upload may send if it frees `replaced` stored bytes once it starts and keeps
+    /// `copied` bytes of an existing file in its temporary copy until it is published
+    #[must_use]
+    pub const fn available(&self, replaced: u64, copied: u64) -> u64 {
+        self.limit
+            .saturating_add(replaced)
+            .saturating_sub(self.used)
+            .saturating_sub(copied)
+    }
+}
+
+/// Fails an upload as soon as it sends more bytes than the quota allows
+pub struct QuotaLimitedReader<R> {
+    inner: R,
+    remaining: u64,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl<R> QuotaLimitedReader<R> {
+    /// The returned flag tells whether the upload failed because of the quota
+    pub fn new(inner: R, available: Option<u64>) -> (Self, Arc<AtomicBool>) {
+        let exceeded = Arc::new(AtomicBool::new(false));
+        (
+            Self {
+                inner,
+                remaining: available.unwrap_or(u64::MAX),
+                exceeded: Arc::clone(&exceeded),
+            },
+            exceeded,
+        )
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for QuotaLimitedReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = (buf.filled().len() - before) as u64;
+
+        if let Some(remaining) = self.remaining.checked_sub(read) {
+            self.remaining = remaining;
+            result
+        } else {
+            // the bytes over the quota must not reach the backend
+            buf.set_filled(before);
+            self.exceeded.store(true, Ordering::Relaxed);
+            Poll::Ready(Err(std::io::Error::other("Quota exceeded")))
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// tests may panic
+#![allow(clippy::unwrap_used)]
+
+#[cfg(test)]
+mod quota_tests {
+    use crate::backend::quota::Quota;
+
+    #[test]
+    fn available_bytes_never_underflow() {
+        let over = Quota {
+            used: 12,
+            limit: 10,
+        };
+
+        assert_eq!(over.available(0, 0), 0);
+        assert_eq!(over.available(4, 0), 2);
+        assert_eq!(over.available(4, 3), 0);
+    }
+
+    #[test]
+    fn replaced_bytes_are_available_again() {
+        let full = Quota {
+            used: 10,
+            limit: 10,
+        };
+
+        assert_eq!(full.available(4, 0), 4);
+        assert_eq!(
+            Quota {
+                used: 0,
+                limit: u64::MAX
+            }
+            .available(1, 0),
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn copied_bytes_are_reserved() {
+        let quota = Quota {
+            used: 10,
+            limit: 20,
+        };
+
+        assert_eq!(quota.available(0, 4), 6);
+        assert_eq!(quota.available(0, 11), 0);
+    }
+}
+
+#[cfg(test)]
+mod quota_limited_reader_tests {
+    use crate::backend::quota::QuotaLimitedReader;
+    use std::sync::atomic::Ordering;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn uploads_within_the_quota_pass_through() {
+        let (mut reader, exceeded) = QuotaLimitedReader::new(&b"hello"[..], Some(5));
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).await.unwrap();
+
+        assert_eq!(read, b"hello");
+        assert!(!exceeded.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn uploads_over_the_quota_fail() {
+        let (mut reader, exceeded) = QuotaLimitedReader::new(&b"hello"[..], Some(4));
+        let mut read = Vec::new();
+
+        assert!(reader.read_to_end(&mut read).await.is_err());
+        assert!(exceeded.load(Ordering::Relaxed));
+    }
+}
@@ -1,32 +1,708 @@
 use super::{
-    metadata::InodeMetaData,
+    checksum::{ChecksumCache, DigestingReader, HashAlgorithm, UploadDigest},
+    listing_cache::ListingCache,
+    metadata::{mapped_group, GroupMapping, InodeMetaData},
+    quota::{Quota, QuotaLimitedReader},
     utils::{
         get_parent_and_name, path_contains_rclone_modification_date, transform_to_ftp_error,
         validate_and_normalize_path,
     },
 };
-use crate::auth::user::FileFighterUser;
+use crate::{
+    auth::{login_cache::LoginCache, user::FileFighterUser},
+    metrics::CORRUPT_UPLOADS,
+    shutdown::{GracefulShutdown, TrackedReader, TransferGuard},
+};
 use async_trait::async_trait;
+use chrono::NaiveDateTime;
 use filefighter_api::ffs_api::{
     endpoints::{
         create_directory, delete_inode, download_file, get_contents_of_folder, get_inode,
-        move_inode, rename_inode, set_last_modified_of_inode, upload_file,
+        get_token_for_password_hash, move_inode, rename_inode, set_last_modified_of_inode,
+        upload_file, upload_file_at_offset,
     },
-    ApiConfig,
+    models::{contents_resource::ContentsResource, inode_resource::InodeResource},
+    ApiConfig, ApiError, Result as ApiResult,
 };
 use libunftp::storage::{
-    Error, ErrorKind, Fileinfo, Metadata, Result, StorageBackend, FEATURE_RESTART,
+    Error, ErrorKind, Fileinfo, Metadata, Result, StorageBackend, FEATURE_RESTART, FEATURE_SITEMD5,
 };
 use std::{
-    fmt::Debug,
+    fmt::{Debug, Display},
+    future::Future,
+    io::Cursor,
     path::{Path, PathBuf},
+    str::FromStr,
+    sync::{atomic::Ordering, Arc, PoisonError},
 };
 use tokio::io::AsyncRead;
-use tracing::{debug, error, instrument, warn};
+use tracing::{debug, error, info, instrument, warn};
+use uuid::Uuid;
+
+/// Name prefix of uploads in progress with `safe_uploads`, these are hidden from listings
+const TEMP_UPLOAD_PREFIX: &str = ".ftp-upload-";
+/// Name prefix of files being overwritten, hidden from listings like uploads in progress
+const REPLACED_PREFIX: &str = ".ftp-replaced-";
 
 #[derive(Debug)]
 pub struct FileFighter {
     pub api_config: ApiConfig,
+    /// Shared with the authenticator, `None` if caching is disabled
+    pub login_cache: Option<Arc<LoginCache>>,
+    /// Tracks running transfers so they can finish before the server shuts down
+    pub shutdown: Arc<GracefulShutdown>,
+    /// Group names shown in listings for shared folders
+    pub group_mappings: Arc<Vec<GroupMapping>>,
+    /// Shared between all sessions
+    pub checksum_cache: Arc<ChecksumCache>,
+    /// Shared between all sessions, `None` if caching is disabled
+    pub listing_cache: Option<Arc<ListingCache>>,
+    /// Delete uploads whose stored size doesn't match the received bytes
+    pub delete_corrupt_uploads: bool,
+    /// Upload to a hidden temporary name and rename it into place once complete
+    pub safe_uploads: bool,
+    pub rmd_policy: RmdPolicy,
+    pub rename_policy: RenamePolicy,
+    /// Bytes every user may store, `None` if storage isn't limited
+    pub user_quota: Option<u64>,
+}
+
+/// What `RMD` does with a folder that isn't empty
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RmdPolicy {
+    /// Reply 550 like a POSIX `rmdir`
+    #[default]
+    Refuse,
+    /// Delete the folder with everything in it on every `RMD`, libunftp doesn't pass
+    /// `SITE RMDIR -r` to the backend, so this can't be limited to an explicit request
+    Recursive,
+}
+
+impl FromStr for RmdPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> std::result::Result<Self, Self::Err> {
+        match policy.to_ascii_lowercase().as_str() {
+            "refuse" => Ok(Self::Refuse),
+            "recursive" => Ok(Self::Recursive),
+            _ => Err(format!(
+                "Unknown rmd policy '{policy}', expected refuse or recursive"
+            )),
+        }
+    }
+}
+
+impl Display for RmdPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Refuse => write!(f, "refuse"),
+            Self::Recursive => write!(f, "recursive"),
+        }
+    }
+}
+
+/// What `RNTO` does if the target path already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenamePolicy {
+    /// Reply 553 and leave both inodes untouched
+    #[default]
+    Refuse,
+    /// Replace an existing file, folders are never replaced
+    Overwrite,
+}
+
+impl FromStr for RenamePolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> std::result::Result<Self, Self::Err> {
+        match policy.to_ascii_lowercase().as_str() {
+            "refuse" => Ok(Self::Refuse),
+            "overwrite" => Ok(Self::Overwrite),
+            _ => Err(format!(
+                "Unknown rename policy '{policy}', expected refuse or overwrite"
+            )),
+        }
+    }
+}
+
+impl Display for RenamePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Refuse => write!(f, "refuse"),
+            Self::Overwrite => write!(f, "overwrite"),
+        }
+    }
+}
+
+impl FileFighter {
+    /// Set the modification time of the inode at path (interpreted as UTC), for the rclone
+    /// path trick in `metadata`
+    #[instrument(skip(self), level = "debug")]
+    async fn set_modified<P: AsRef<Path> + Send + Debug>(
+        &self,
+        user: &FileFighterUser,
+        path: P,
+        modified: NaiveDateTime,
+    ) -> Result<InodeMetaData> {
+        check_write_access(user)?;
+        let path = &validate_and_normalize_path(path)?;
+        let api_config = &self.api_config;
+        let timestamp = modified.timestamp();
+
+        let inode = self
+            .with_token(user, |token| async move {
+                set_last_modified_of_inode(api_config, &token, path, timestamp).await
+            })
+            .await;
+        self.forget_listings(path);
+        let inode = inode?;
+
+        Ok(InodeMetaData::from(&inode, user.id, &user.username))
+    }
+
+    /// Delete the inode and everything below it, logging what was removed
+    ///
+    /// libunftp replies to `DELE` and `RMD` with a fixed text, so the log is the only place
+    /// the removed inodes can be reported.
+    async fn delete(&self, user: &FileFighterUser, path: &Path) -> Result<()> {
+        let api_config = &self.api_config;
+        let deleted = self
+            .with_token(user, |token| async move {
+                delete_inode(api_config, &token, path).await
+            })
+            .await;
+        self.forget_listings(path);
+        let deleted = deleted?;
+
+        info!(
+            user = %user,
+            path = %path.display(),
+            deleted = ?deleted.iter().map(|inode| inode.path.as_str()).collect::<Vec<_>>(),
+            "Deleted {} inodes",
+            deleted.len()
+        );
+        Ok(())
+    }
+
+    /// Bytes the user may upload to path, `None` if the user's storage isn't limited
+    ///
+    /// An upload that overwrites the stored file in place frees its bytes. An upload to a
+    /// temporary copy keeps the stored file until it is published, and a resumed one also
+    /// copies the first `start_pos` bytes of it.
+    async fn available_space(
+        &self,
+        user: &FileFighterUser,
+        path: &Path,
+        start_pos: u64,
+        to_temp_copy: bool,
+    ) -> Result<Option<u64>> {
+        let Some(limit) = self.user_quota else {
+            return Ok(None);
+        };
+
+        // folders report the size of everything in them
+        let api_config = &self.api_config;
+        let stored = self
+            .with_token(user, |token| async move {
+                get_contents_of_folder(api_config, &token, Path::new("/")).await
+            })
+            .await?
+            .inodes
+            .iter()
+            .map(|inode| inode.size)
+            .fold(0, u64::saturating_add);
+        let quota = Quota {
+            used: stored,
+            limit,
+        };
+
+        if to_temp_copy {
+            return Ok(Some(quota.available(0, start_pos)));
+        }
+        let replaced = self
+            .find_inode(user, path)
+            .await?
+            .filter(|inode| inode.mime_type.is_some())
+            .map_or(0, |inode| inode.size);
+
+        Ok(Some(quota.available(replaced, 0)))
+    }
+
+    /// Contents of the folder at path, from the listing cache if possible
+    async fn folder_contents(
+        &self,
+        user: &FileFighterUser,
+        path: &Path,
+    ) -> Result<Arc<ContentsResource>> {
+        let cache = self.listing_cache.as_deref();
+        if let Some(contents) = cache.and_then(|cache| cache.contents(user.id, path)) {
+            return Ok(contents);
+        }
+
+        let api_config = &self.api_config;
+        let contents = Arc::new(
+            self.with_token(user, |token| async move {
+                get_contents_of_folder(api_config, &token, path).await
+            })
+            .await?,
+        );
+        if let Some(cache) = cache {
+            cache.insert_contents(user.id, path, contents.clone());
+        }
+        Ok(contents)
+    }
+
+    /// The inode at path, from the listing cache if possible
+    ///
+    /// Commands that change inodes look them up without the cache, they need the current state.
+    async fn cached_inode(
+        &self,
+        user: &FileFighterUser,
+        path: &Path,
+    ) -> Result<Arc<InodeResource>> {
+        let cache = self.listing_cache.as_deref();
+        if let Some(inode) = cache.and_then(|cache| cache.inode(user.id, path)) {
+            return Ok(inode);
+        }
+
+        let api_config = &self.api_config;
+        let inode = Arc::new(
+            self.with_token(user, |token| async move {
+                get_inode(api_config, path, &token).await
+            })
+            .await?,
+        );
+        if let Some(cache) = cache {
+            cache.insert_inode(user.id, path, inode.clone());
+        }
+        Ok(inode)
+    }
+
+    /// Called after every change, whether it succeeded or not
+    fn forget_listings(&self, path: &Path) {
+        if let Some(cache) = &self.listing_cache {
+            cache.invalidate(path);
+        }
+    }
+
+    /// Look up an inode, `None` if there is nothing at the path
+    async fn find_inode(
+        &self,
+        user: &FileFighterUser,
+        path: &Path,
+    ) -> Result<Option<InodeResource>> {
+        let api_config = &self.api_config;
+        match self
+            .with_token(user, |token| async move {
+                get_inode(api_config, path, &token).await
+            })
+            .await
+        {
+            Ok(inode) => Ok(Some(inode)),
+            Err(err) if err.kind() == ErrorKind::PermanentFileNotAvailable => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Rename the inode at path within its folder and return the new path
+    async fn rename_within_folder(
+        &self,
+        user: &FileFighterUser,
+        path: &Path,
+        new_name: &str,
+    ) -> Result<PathBuf> {
+        let api_config = &self.api_config;
+        let renamed = self
+            .with_token(user, |token| async move {
+                rename_inode(api_config, &token, path, new_name).await
+            })
+            .await;
+        self.forget_listings(path);
+        if let Some(parent) = path.parent() {
+            self.forget_listings(&parent.join(new_name));
+        }
+        Ok(PathBuf::from(renamed?.path))
+    }
+
+    /// Move the inode at path into another folder and return the new path
+    async fn move_to_folder(
+        &self,
+        user: &FileFighterUser,
+        path: &Path,
+        new_parent: &Path,
+    ) -> Result<PathBuf> {
+        let api_config = &self.api_config;
+        let moved = self
+            .with_token(user, |token| async move {
+                move_inode(api_config, &token, path, new_parent).await
+            })
+            .await;
+        self.forget_listings(path);
+        if let Some(name) = path.file_name() {
+            self.forget_listings(&new_parent.join(name));
+        }
+        Ok(PathBuf::from(moved?.path))
+    }
+
+    /// Move and rename an inode to a free path, either completely or not at all
+    ///
+    /// The intermediate path must be free as well, so the step order depends on which
+    /// of the two intermediate paths is unused. If the second step fails the first one
+    /// is reverted.
+    async fn relocate(&self, user: &FileFighterUser, from: &Path, to: &Path) -> Result<()> {
+        let (from_parent, from_name) = get_parent_and_name(from)?;
+        let (to_parent, to_name) = get_parent_and_name(to)?;
+
+        if from_parent == to_parent {
+            return self
+                .rename_within_folder(user, from, to_name)
+                .await
+                .map(drop);
+        }
+        if from_name == to_name {
+            return self.move_to_folder(user, from, &to_parent).await.map(drop);
+        }
+
+        let rename_first = self
+            .find_inode(user, &from_parent.join(to_name))
+            .await?
+            .is_none();
+
+        if rename_first {
+            let renamed = self.rename_within_folder(user, from, to_name).await?;
+            if let Err(err) = self.move_to_folder(user, &renamed, &to_parent).await {
+                self.revert(
+                    user,
+                    "rename",
+                    self.rename_within_folder(user, &renamed, from_name),
+                )
+                .await;
+                return Err(err);
+            }
+        } else {
+            let moved = self.move_to_folder(user, from, &to_parent).await?;
+            if let Err(err) = self.rename_within_folder(user, &moved, to_name).await {
+                self.revert(
+                    user,
+                    "move",
+                    self.move_to_folder(user, &moved, &from_parent),
+                )
+                .await;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Overwrite the file at `to` with the one at `from`
+    ///
+    /// The existing file is renamed aside first and only deleted once the source took its
+    /// place, so a failure leaves both files where they were.
+    async fn replace(
+        &self,
+        user: &FileFighterUser,
+        from: &Path,
+        to: &Path,
+        target: &InodeResource,
+    ) -> Result<()> {
+        let (_, to_name) = get_parent_and_name(to)?;
+        let backup_name = format!("{REPLACED_PREFIX}{}", target.id);
+        let backup = self.rename_within_folder(user, to, &backup_name).await?;
+
+        if let Err(err) = self.relocate(user, from, to).await {
+            self.revert(
+                user,
+                "overwrite",
+                self.rename_within_folder(user, &backup, to_name),
+            )
+            .await;
+            return Err(err);
+        }
+
+        info!(user = %user, from = %from.display(), to = %to.display(), "Overwriting existing file");
+        // the overwrite itself succeeded, a leftover backup is hidden from listings
+        if let Err(err) = self.delete(user, &backup).await {
+            warn!(
+                "Could not remove overwritten file {}: {}",
+                backup.display(),
+                err
+            );
+        }
+        Ok(())
+    }
+
+    /// Put a complete safe upload in place of the target, replacing an existing file
+    async fn publish_upload(
+        &self,
+        user: &FileFighterUser,
+        temp_path: &Path,
+        path: &Path,
+    ) -> Result<()> {
+        match self.find_inode(user, path).await? {
+            None => self.relocate(user, temp_path, path).await,
+            Some(target) if target.mime_type.is_some() => {
+                self.replace(user, temp_path, path, &target).await
+            }
+            Some(_) => Err(Error::new(
+                ErrorKind::FileNameNotAllowedError,
+                "A directory with this name already exists.",
+            )),
+        }
+    }
+
+    /// Remove the temporary file of a failed safe upload, if it got created
+    async fn discard_upload(&self, user: &FileFighterUser, temp_path: &Path) {
+        match self.find_inode(user, temp_path).await {
+            Ok(Some(_)) => {
+                if let Err(err) = self.delete(user, temp_path).await {
+                    warn!(
+                        "Could not remove temporary upload {}: {}",
+                        temp_path.display(),
+                        err
+                    );
+                }
+            }
+            Ok(None) => {}
+            Err(err) => warn!(
+                "Could not look up temporary upload {}: {}",
+                temp_path.display(),
+                err
+            ),
+        }
+    }
+
+    /// Undo a step of a failed rename, there is nothing left to do if that fails as well
+    async fn revert(
+        &self,
+        user: &FileFighterUser,
+        step: &str,
+        undo: impl Future<Output = Result<PathBuf>> + Send,
+    ) {
+        match undo.await {
+            Ok(path) => warn!(
+                user = %user,
+                path = %path.display(),
+                "Reverted {} of a failed rename",
+                step
+            ),
+            Err(err) => error!(
+                user = %user,
+                "Could not revert {} of a failed rename, inode is left behind: {}",
+                step,
+                err
+            ),
+        }
+    }
+
+    /// Fetch the uploaded inode and verify it, returns the stored size
+    async fn check_upload(
+        &self,
+        user: &FileFighterUser,
+        path: &Path,
+        start_pos: u64,
+        digest: &UploadDigest,
+    ) -> Result<u64> {
+        let api_config = &self.api_config;
+        let inode = self
+            .with_token(user, |token| async move {
+                get_inode(api_config, path, &token).await
+            })
+            .await?;
+
+        self.verify_upload(user, path, start_pos, digest, &inode)
+            .await?;
+        Ok(inode.size)
+    }
+
+    /// Compare the stored inode with the bytes received from the client
+    ///
+    /// The `FileHandlerService` only reports the size, so that is what gets compared. The hash
+    /// of a complete upload is remembered for later checksum requests.
+    async fn verify_upload(
+        &self,
+        user: &FileFighterUser,
+        path: &Path,
+        start_pos: u64,
+        digest: &UploadDigest,
+        inode: &InodeResource,
+    ) -> Result<()> {
+        let expected_size = start_pos + digest.size;
+        let sha256 = digest.sha256();
+
+        if inode.size != expected_size {
+            CORRUPT_UPLOADS.inc();
+            warn!(
+                event = "upload_integrity",
+                verified = false,
+                user = %user,
+                path = %path.display(),
+                start_pos,
+                received_bytes = digest.size,
+                expected_size,
+                stored_size = inode.size,
+                sha256 = %sha256,
+                deleted = self.delete_corrupt_uploads,
+                "Stored file does not match the upload"
+            );
+
+            if self.delete_corrupt_uploads {
+                let api_config = &self.api_config;
+                if let Err(err) = self
+                    .with_token(user, |token| async move {
+                        delete_inode(api_config, &token, path).await
+                    })
+                    .await
+                {
+                    warn!(
+                        "Could not delete corrupt upload {}: {}",
+                        path.display(),
+                        err
+                    );
+                }
+            }
+
+            return Err(Error::new(
+                ErrorKind::LocalError,
+                "Stored file does not match the upload.",
+            ));
+        }
+
+        debug!(
+            event = "upload_integrity",
+            verified = true,
+            path = %path.display(),
+            size = inode.size,
+            sha256 = %sha256,
+            "Upload verified"
+        );
+        if start_pos == 0 {
+            self.checksum_cache.insert(
+                &inode.id,
+                inode.last_updated,
+                HashAlgorithm::Sha256,
+                sha256,
+            );
+        }
+        Ok(())
+    }
+
+    /// Checksum of the file at path, as lower case hex
+    ///
+    /// The file is streamed from the `FileHandlerService` unless the checksum of this
+    /// version of the inode is cached. Reachable through `SITE MD5`, the `HASH` and `X*`
+    /// commands aren't dispatched to storage backends by libunftp 0.18.
+    ///
+    /// # Errors
+    /// Fails if the path is not a file or the download fails
+    #[instrument(skip(self), level = "debug")]
+    pub async fn checksum<P: AsRef<Path> + Send + Debug>(
+        &self,
+        user: &FileFighterUser,
+        path: P,
+        algorithm: HashAlgorithm,
+    ) -> Result<String> {
+        let path = &validate_and_normalize_path(path)?;
+        let api_config = &self.api_config;
+        let inode = self
+            .with_token(user, |token| async move {
+                get_inode(api_config, path, &token).await
+            })
+            .await?;
+
+        if inode.mime_type.is_none() {
+            return Err(Error::new(
+                ErrorKind::PermanentFileNotAvailable,
+                "Checksums can only be computed for files.",
+            ));
+        }
+        if let Some(checksum) = self
+            .checksum_cache
+            .get(&inode.id, inode.last_updated, algorithm)
+        {
+            debug!("Reusing cached {} checksum", algorithm.name());
+            return Ok(checksum);
+        }
+
+        let reader = self.get(user, path, 0).await?;
+        let checksum = algorithm
+            .digest(reader)
+            .await
+            .map_err(|err| Error::new(ErrorKind::LocalError, err))?;
+        self.checksum_cache
+            .insert(&inode.id, inode.last_updated, algorithm, checksum.clone());
+
+        Ok(checksum)
+    }
+
+    /// Register a new transfer, refusing it if the server is shutting down
+    fn start_transfer(&self) -> Result<TransferGuard> {
+        self.shutdown
+            .start_transfer()
+            .ok_or_else(|| Error::new(ErrorKind::LocalError, "Server is shutting down"))
+    }
+
+    /// Transform the error into a ftp error and forget the login if the backend rejected its token
+    fn transform_error(&self, user: &FileFighterUser, error: ApiError) -> Error {
+        if let (ApiError::Unauthorized(_), Some(cache)) = (&error, &self.login_cache) {
+            cache.invalidate_token(&user.token());
+        }
+        transform_to_ftp_error(error)
+    }
+
+    /// Get a new token with the credentials of the session, e.g. after the old one expired
+    async fn renew_token(&self, user: &FileFighterUser) -> Result<String> {
+        let old_token = user.token();
+        let token =
+            get_token_for_password_hash(&self.api_config, &user.username, user.password_hash())
+                .await
+                .map_err(|err| self.transform_error(user, err))?;
+
+        debug!("Renewed token of {}", user);
+        if let Some(cache) = &self.login_cache {
+            cache.replace_token(&old_token, &token);
+        }
+        user.set_token(token.clone());
+        Ok(token)
+    }
+
+    /// Call the backend with the token of the session.
+    ///
+    /// If the backend rejects the token it gets renewed and the call is repeated once.
+    async fn with_token<T, F, Fut>(&self, user: &FileFighterUser, call: F) -> Result<T>
+    where
+        F: Fn(String) -> Fut + Send + Sync,
+        Fut: Future<Output = ApiResult<T>> + Send,
+        T: Send,
+    {
+        match call(user.token()).await {
+            Err(ApiError::Unauthorized(err)) => {
+                debug!("Token was rejected: {}", err);
+                let token = self.renew_token(user).await?;
+                call(token)
+                    .await
+                    .map_err(|err| self.transform_error(user, err))
+            }
+            result => result.map_err(|err| self.transform_error(user, err)),
+        }
+    }
+}
+
+/// Uploads in progress and files being overwritten aren't shown to clients
+fn is_hidden(inode: &InodeResource) -> bool {
+    inode.name.starts_with(TEMP_UPLOAD_PREFIX) || inode.name.starts_with(REPLACED_PREFIX)
+}
+
+/// Refuse commands that change inodes for users with read only privileges
+fn check_write_access(user: &FileFighterUser) -> Result<()> {
+    if user.privileges.can_write() {
+        Ok(())
+    } else {
+        warn!("Refusing write access of read only user {}", user);
+        Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "User has read only access.",
+        ))
+    }
 }
 
 #[async_trait]
@@ -36,13 +712,24 @@ impl StorageBackend<FileFighterUser> for FileFighter {
     #[allow(clippy::unreachable)]
     #[instrument(skip(self), level = "debug")]
     fn supported_features(&self) -> u32 {
-        FEATURE_RESTART
+        FEATURE_RESTART | FEATURE_SITEMD5
+    }
+
+    async fn md5<P: AsRef<Path> + Send + Debug>(
+        &self,
+        user: &FileFighterUser,
+        path: P,
+    ) -> Result<String>
+    where
+        P: AsRef<Path> + Send + Debug,
+    {
+        self.checksum(user, path, HashAlgorithm::Md5).await
     }
 
     /// Endpoint to request Metadata for a inode
     ///
     /// # Rclone
-    /// In some cases the path consists of /<date>/ /acutal_path.
+    /// In some cases the path consists of `/<date>/ /acutal_path`.
     /// This means that rclone wants to update the modification date of that inode at the path
     #[instrument(skip(self), level = "debug")]
     async fn metadata<P: AsRef<Path> + Send + Debug>(
@@ -52,30 +739,16 @@ impl StorageBackend<FileFighterUser> for FileFighter {
     ) -> Result<Self::Metadata> {
         let path = path.as_ref();
 
-        let inode = match path_contains_rclone_modification_date(path) {
+        if let Some((timestamp, path)) = path_contains_rclone_modification_date(path) {
             // rclone wants to update time
-            Some(tuple) => {
-                set_last_modified_of_inode(
-                    &self.api_config,
-                    &user.token,
-                    &tuple.1,
-                    tuple.0.timestamp(),
-                )
-                .await
-            }
-            // regular metadata request
-            None => {
-                get_inode(
-                    &self.api_config,
-                    &validate_and_normalize_path(path)?,
-                    &user.token,
-                )
-                .await
-            }
+            return self.set_modified(user, path, timestamp).await;
         }
-        .map_err(transform_to_ftp_error)?;
 
-        Ok(InodeMetaData::from(&inode, user.id))
+        // regular metadata request
+        let path = &validate_and_normalize_path(path)?;
+        let inode = self.cached_inode(user, path).await?;
+
+        Ok(InodeMetaData::from(&inode, user.id, &user.username))
     }
 
     #[instrument(skip(self), level = "debug")]
@@ -88,19 +761,49 @@ impl StorageBackend<FileFighterUser> for FileFighter {
         P: AsRef<Path> + Send + Debug,
         <Self as StorageBackend<FileFighterUser>>::Metadata: Metadata,
     {
-        let path = validate_and_normalize_path(path)?;
-        let contents = get_contents_of_folder(&self.api_config, &user.token, &path)
-            .await
-            .map_err(transform_to_ftp_error)?;
+        let path = &validate_and_normalize_path(path)?;
+        let contents = self.folder_contents(user, path).await?;
 
         debug!("Found {} inodes", contents.inodes.len());
 
         Ok(contents
             .inodes
             .iter()
+            .filter(|inode| !is_hidden(inode))
             .map(|inode| Fileinfo {
                 path: PathBuf::from(&inode.path),
-                metadata: InodeMetaData::from(inode, contents.owner.id),
+                metadata: InodeMetaData::from(inode, contents.owner.id, &contents.owner.username),
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn list_fmt<P>(&self, user: &FileFighterUser, path: P) -> Result<Cursor<Vec<u8>>>
+    where
+        P: AsRef<Path> + Send + Debug,
+        Self::Metadata: Metadata + 'static,
+    {
+        let mut listing = self.list_vec(user, path).await?.join("\r\n");
+        if !listing.is_empty() {
+            listing.push_str("\r\n");
+        }
+        Ok(Cursor::new(listing.into_bytes()))
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    async fn list_vec<P>(&self, user: &FileFighterUser, path: P) -> Result<Vec<String>>
+    where
+        P: AsRef<Path> + Send + Debug,
+        Self::Metadata: Metadata + 'static,
+    {
+        Ok(self
+            .list(user, path)
+            .await?
+            .iter()
+            .filter_map(|info| {
+                let name = info.path.file_name()?.to_string_lossy();
+                let group = mapped_group(&self.group_mappings, &info.path);
+                Some(info.metadata.list_line(&name, group))
             })
             .collect())
     }
@@ -112,20 +815,16 @@ impl StorageBackend<FileFighterUser> for FileFighter {
         path: P,
         start_pos: u64,
     ) -> Result<Box<dyn AsyncRead + Send + Sync + Unpin>> {
-        // IDEA: maybe implement this by skipping the first bytes
-        if start_pos != 0 {
-            error!("Gets at offset not equal to 0 are not implemented.");
-            return Err(Error::new(
-                ErrorKind::CommandNotImplemented,
-                "Gets at offset not equal to 0 are not implemented.",
-            ));
-        }
-
-        let path = validate_and_normalize_path(path)?;
+        let guard = self.start_transfer()?;
+        let path = &validate_and_normalize_path(path)?;
+        let api_config = &self.api_config;
 
-        download_file(&self.api_config, &user.token, &path)
-            .await
-            .map_err(transform_to_ftp_error)
+        let reader = self
+            .with_token(user, |token| async move {
+                download_file(api_config, &token, path, start_pos).await
+            })
+            .await?;
+        Ok(Box::new(TrackedReader::new(reader, guard)))
     }
 
     #[instrument(skip(self, bytes))]
@@ -140,27 +839,90 @@ impl StorageBackend<FileFighterUser> for FileFighter {
         FilePath: AsRef<Path> + Send + Debug,
         ByteStream: AsyncRead + Send + Sync + 'static + Unpin,
     {
-        // TODO: remove this by implementing
-        if start_pos != 0 {
-            error!("Puts at offset not equal to 0 are not implemented.");
+        check_write_access(user)?;
+        let _guard = self.start_transfer()?;
+        let path = &validate_and_normalize_path(path)?;
+        let (parent_path, name) = get_parent_and_name(path)?;
+        let parent_path = &parent_path;
+        let api_config = &self.api_config;
+
+        // the upload can't be repeated, so make sure the token is valid before streaming
+        self.with_token(user, |token| async move {
+            get_inode(api_config, parent_path, &token).await
+        })
+        .await?;
+
+        // resumed uploads read the existing file while uploading, so they can't replace it
+        let temp_name = (self.safe_uploads || start_pos != 0)
+            .then(|| format!("{TEMP_UPLOAD_PREFIX}{}", Uuid::new_v4()));
+
+        // libunftp ignores ALLO, so the size isn't known before the upload
+        let available = self
+            .available_space(user, path, start_pos, temp_name.is_some())
+            .await?;
+        if available == Some(0) {
             return Err(Error::new(
-                ErrorKind::CommandNotImplemented,
-                "Puts at offset not equal to 0 are not implemented.",
+                ErrorKind::ExceededStorageAllocationError,
+                "Quota exceeded.",
             ));
         }
 
-        let path = validate_and_normalize_path(path)?;
-        let (parent_path, name) = get_parent_and_name(&path)?;
+        let upload_name = temp_name.as_deref().unwrap_or(name);
+        let upload_path = &parent_path.join(upload_name);
 
-        upload_file(&self.api_config, &user.token, &parent_path, name, bytes)
+        let (bytes, quota_exceeded) = QuotaLimitedReader::new(bytes, available);
+        let (bytes, digest) = DigestingReader::new(bytes);
+        let token = user.token();
+        let upload = if start_pos == 0 {
+            upload_file(api_config, &token, parent_path, upload_name, name, bytes).await
+        } else {
+            upload_file_at_offset(
+                api_config,
+                &token,
+                parent_path,
+                name,
+                start_pos,
+                upload_name,
+                bytes,
+            )
             .await
-            .map_err(transform_to_ftp_error)?;
+        };
+        self.forget_listings(upload_path);
 
-        let inode = get_inode(&self.api_config, &path, &user.token)
-            .await
-            .map_err(transform_to_ftp_error)?;
+        let result = match upload {
+            Ok(inodes) => {
+                // an overwritten file keeps its id
+                for inode in &inodes {
+                    self.checksum_cache.forget(&inode.id);
+                }
+                let digest = digest
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone();
+                self.check_upload(user, upload_path, start_pos, &digest)
+                    .await
+            }
+            Err(_) if quota_exceeded.load(Ordering::Relaxed) => Err(Error::new(
+                ErrorKind::ExceededStorageAllocationError,
+                "Quota exceeded.",
+            )),
+            Err(err) => Err(self.transform_error(user, err)),
+        };
 
-        Ok(inode.size)
+        if temp_name.is_none() {
+            return result;
+        }
+        let result = match result {
+            Ok(size) => self
+                .publish_upload(user, upload_path, path)
+                .await
+                .map(|()| size),
+            Err(err) => Err(err),
+        };
+        if result.is_err() {
+            self.discard_upload(user, upload_path).await;
+        }
+        result
     }
 
     #[instrument(skip(self), level = "debug")]
@@ -169,12 +931,23 @@ impl StorageBackend<FileFighterUser> for FileFighter {
         user: &FileFighterUser,
         path: P,
     ) -> Result<()> {
-        // Should this check if the inode to delete is really a file?
-        let path = validate_and_normalize_path(path)?;
-        delete_inode(&self.api_config, &user.token, &path)
-            .await
-            .map_err(transform_to_ftp_error)?;
-        Ok(())
+        check_write_access(user)?;
+        let path = &validate_and_normalize_path(path)?;
+        let api_config = &self.api_config;
+        let inode = self
+            .with_token(user, |token| async move {
+                get_inode(api_config, path, &token).await
+            })
+            .await?;
+
+        if inode.mime_type.is_none() {
+            return Err(Error::new(
+                ErrorKind::PermanentFileNotAvailable,
+                "Path is a directory, use RMD to remove it.",
+            ));
+        }
+
+        self.delete(user, path).await
     }
 
     #[instrument(skip(self), level = "debug")]
@@ -183,17 +956,26 @@ impl StorageBackend<FileFighterUser> for FileFighter {
         user: &FileFighterUser,
         path: P,
     ) -> Result<()> {
+        check_write_access(user)?;
         let path = validate_and_normalize_path(path)?;
         let (parent_path, name) = get_parent_and_name(&path)?;
+        let parent_path = &parent_path;
+        let api_config = &self.api_config;
 
-        create_directory(&self.api_config, &user.token, parent_path.as_path(), name)
+        let created = self
+            .with_token(user, |token| async move {
+                create_directory(api_config, &token, parent_path, name).await
+            })
             .await
-            .map_err(transform_to_ftp_error)?;
-        Ok(())
+            .map(drop);
+        self.forget_listings(&path);
+        created
     }
 
     /// Used to rename and move inodes.
-    /// TODO: fix this by implementing a custom endpoint in the fss
+    ///
+    /// The `FileSystemService` has no combined call, so a cross folder rename is a rename
+    /// followed by a move that gets undone if the second step fails.
     #[instrument(skip(self), level = "debug")]
     async fn rename<P: AsRef<Path> + Send + Debug>(
         &self,
@@ -201,57 +983,92 @@ impl StorageBackend<FileFighterUser> for FileFighter {
         from: P,
         to: P,
     ) -> Result<()> {
-        let mut from_path = validate_and_normalize_path(from)?;
+        check_write_access(user)?;
+        let from_path = validate_and_normalize_path(from)?;
         let to_path = validate_and_normalize_path(to)?;
 
-        let (from_parent, from_name) = get_parent_and_name(&from_path)?;
-        let (to_parent, to_name) = get_parent_and_name(&to_path)?;
+        if from_path == to_path {
+            return Ok(());
+        }
 
-        if from_name != to_name {
-            let new_path = rename_inode(&self.api_config, &user.token, &from_path, to_name)
-                .await
-                .map_err(transform_to_ftp_error)?
-                .path;
-            from_path = PathBuf::from(new_path);
-        };
+        let source = self.find_inode(user, &from_path).await?.ok_or_else(|| {
+            Error::new(
+                ErrorKind::PermanentFileNotAvailable,
+                "Source does not exist.",
+            )
+        })?;
 
-        if from_parent != to_parent {
-            move_inode(&self.api_config, &user.token, &from_path, &to_parent)
-                .await
-                .map_err(transform_to_ftp_error)?;
+        let Some(target) = self.find_inode(user, &to_path).await? else {
+            return self.relocate(user, &from_path, &to_path).await;
         };
 
-        Ok(())
+        if self.rename_policy == RenamePolicy::Refuse {
+            return Err(Error::new(
+                ErrorKind::FileNameNotAllowedError,
+                "Target already exists.",
+            ));
+        }
+        if source.mime_type.is_none() || target.mime_type.is_none() {
+            return Err(Error::new(
+                ErrorKind::FileNameNotAllowedError,
+                "Only files can be overwritten.",
+            ));
+        }
+
+        self.replace(user, &from_path, &to_path, &target).await
     }
 
-    // IDEA: check if inode at path is a directory
     #[instrument(skip(self), level = "debug")]
     async fn rmd<P: AsRef<Path> + Send + Debug>(
         &self,
         user: &FileFighterUser,
         path: P,
     ) -> Result<()> {
-        let path = validate_and_normalize_path(path)?;
-        delete_inode(&self.api_config, &user.token, &path)
-            .await
-            .map_err(transform_to_ftp_error)?;
-        Ok(())
+        check_write_access(user)?;
+        let path = &validate_and_normalize_path(path)?;
+        let api_config = &self.api_config;
+        let inode = self
+            .with_token(user, |token| async move {
+                get_inode(api_config, path, &token).await
+            })
+            .await?;
+
+        if inode.mime_type.is_some() {
+            return Err(Error::new(
+                ErrorKind::PermanentDirectoryNotAvailable,
+                "Path is a file, use DELE to remove it.",
+            ));
+        }
+
+        if self.rmd_policy == RmdPolicy::Refuse {
+            let contents = self
+                .with_token(user, |token| async move {
+                    get_contents_of_folder(api_config, &token, path).await
+                })
+                .await?;
+            // leftovers of failed uploads would keep a folder that looks empty
+            if !contents.inodes.iter().all(is_hidden) {
+                return Err(Error::new(
+                    ErrorKind::PermanentDirectoryNotEmpty,
+                    "Directory is not empty.",
+                ));
+            }
+        }
+
+        self.delete(user, path).await
     }
 
-    // TODO: check that path is a folder
     #[instrument(skip(self), level = "debug")]
     async fn cwd<P: AsRef<Path> + Send + Debug>(
         &self,
         user: &FileFighterUser,
         path: P,
     ) -> Result<()> {
-        let path = validate_and_normalize_path(path)?;
-        let inode = get_inode(&self.api_config, &path, &user.token)
-            .await
-            .map_err(transform_to_ftp_error)?;
+        let path = &validate_and_normalize_path(path)?;
+        let inode = self.cached_inode(user, path).await?;
 
         // transform to metadata so we can check if its a directory
-        let inode_metadata = InodeMetaData::from(&inode, user.id);
+        let inode_metadata = InodeMetaData::from(&inode, user.id, &user.username);
         if inode_metadata.is_dir() {
             Ok(())
         } else {
@@ -1,11 +1,17 @@
 use chrono::NaiveDateTime;
-use filefighter_api::ffs_api::ApiError::{self, ReqwestError, ResponseMalformed};
+use filefighter_api::ffs_api::ApiError::{
+    self, BadRequest, CircuitOpen, Conflict, Forbidden, NotFound, OffsetBeyondEnd, PayloadTooLarge,
+    ReqwestError, ResponseMalformed, ServiceUnavailable, Unauthorized, UnexpectedStatus,
+};
 use libunftp::storage::{
     Error,
     ErrorKind::{self, FileNameNotAllowedError},
     Result,
 };
-use std::path::{Component, Path, PathBuf};
+use std::{
+    path::{Component, Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 use tracing::{debug, warn};
 
 pub fn get_parent_and_name(path: &Path) -> Result<(PathBuf, &str)> {
@@ -32,9 +38,36 @@ pub fn transform_to_ftp_error(error: ApiError) -> Error {
             warn!("Filesystemservice error response: {}", err);
             Error::new(ErrorKind::PermanentDirectoryNotAvailable, err)
         }
+        NotFound(err) => Error::new(ErrorKind::PermanentFileNotAvailable, err),
+        error @ OffsetBeyondEnd { .. } => {
+            Error::new(ErrorKind::PermanentFileNotAvailable, error.to_string())
+        }
+        // libunftp can't reply with 421 from a storage backend, so a rejected token is a 550
+        Forbidden(err) | Unauthorized(err) => Error::new(ErrorKind::PermissionDenied, err),
+        BadRequest(err) | Conflict(err) => Error::new(ErrorKind::FileNameNotAllowedError, err),
+        PayloadTooLarge(err) => Error::new(ErrorKind::ExceededStorageAllocationError, err),
+        ServiceUnavailable(err) => {
+            warn!("Backend unavailable: {}", err);
+            Error::new(ErrorKind::LocalError, err)
+        }
+        // libunftp can't reply with 421 either, 450 tells the client to try again later
+        CircuitOpen(err) => Error::new(ErrorKind::TransientFileNotAvailable, err),
+        UnexpectedStatus(status, err) => {
+            warn!("Unexpected response code {}: {}", status, err);
+            Error::new(ErrorKind::LocalError, err)
+        }
     }
 }
 
+/// Convert `lastUpdated` of the `FileSystemService`, seconds since the epoch
+///
+/// Seconds are also what `/filesystem/timestamp` takes, which the rclone integration
+/// has always sent.
+pub fn last_updated_to_system_time(last_updated: u64) -> SystemTime {
+    let since_epoch = Duration::from_secs(last_updated);
+    UNIX_EPOCH.checked_add(since_epoch).unwrap_or(UNIX_EPOCH)
+}
+
 // IDEA: check if rclone does try to update the root folder
 pub fn path_contains_rclone_modification_date(path: &Path) -> Option<(NaiveDateTime, PathBuf)> {
     let mut components: Vec<Component> = path.components().collect();
@@ -97,7 +130,7 @@ pub fn validate_and_normalize_path<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
 /// This function ensures a given path ending with '/' still
 /// ends with '/' after normalization.
 fn normalize_path<P: AsRef<Path>>(path: P) -> PathBuf {
-    let ends_with_slash = path.as_ref().to_str().map_or(false, |s| s.ends_with('/'));
+    let ends_with_slash = path.as_ref().to_str().is_some_and(|s| s.ends_with('/'));
     let mut normalized = PathBuf::new();
     for component in path.as_ref().components() {
         match &component {
@@ -1,18 +1,21 @@
+// tests may panic and print freely
+#![allow(clippy::unwrap_used, clippy::print_stdout)]
+
 #[cfg(test)]
 mod path_normalize_tests {
     use crate::backend::utils::validate_and_normalize_path;
 
     fn validation_works(before: &str, after: &str) {
-        println!("Works: --- {} ------------", before);
-        let result = validate_and_normalize_path(before.to_string()).unwrap();
+        println!("Works: --- {before} ------------");
+        let result = validate_and_normalize_path(before).unwrap();
 
         println!("Result: {}", result.display());
         assert_eq!(result.to_string_lossy(), after);
     }
 
     fn validation_fails(before: &str) {
-        println!("Fails: --- {} ------------", before);
-        let result = validate_and_normalize_path(before.to_string());
+        println!("Fails: --- {before} ------------");
+        let result = validate_and_normalize_path(before);
 
         assert!(result.is_err());
     }
@@ -54,7 +57,7 @@ mod rclone_modification_check_tests {
     fn timestamp_parsing_works() {
         let result = NaiveDateTime::parse_from_str("20221003093709", "%Y%m%d%H%M%S").unwrap();
         let resulting_string = result.to_string();
-        assert_eq!("2022-10-03 09:37:09", resulting_string)
+        assert_eq!("2022-10-03 09:37:09", resulting_string);
     }
 
     #[test]
@@ -78,20 +81,75 @@ mod rclone_modification_check_tests {
     fn path_contains_rclone_modification_date_fails_without_whitespace() {
         let path = PathBuf::from_str("/20221003093709/Home/School").unwrap();
         let option = path_contains_rclone_modification_date(&path);
-        assert!(option.is_none())
+        assert!(option.is_none());
     }
 
     #[test]
     fn path_contains_rclone_modification_date_fails_with_wrong_timestamp_format() {
         let path = PathBuf::from_str("/202210030937 /Home/School").unwrap();
         let option = path_contains_rclone_modification_date(&path);
-        assert!(option.is_none())
+        assert!(option.is_none());
     }
 
     #[test]
     fn path_contains_rclone_modification_date_fails_with_wrong_timestamp() {
         let path = PathBuf::from_str("/20221003093790 /Home/School").unwrap();
         let option = path_contains_rclone_modification_date(&path);
-        assert!(option.is_none())
+        assert!(option.is_none());
+    }
+}
+
+#[cfg(test)]
+mod timestamp_tests {
+    use crate::backend::utils::last_updated_to_system_time;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn last_updated_seconds_are_converted() {
+        assert_eq!(
+            last_updated_to_system_time(1_600_000_000),
+            UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+        );
+    }
+
+    #[test]
+    fn last_updated_does_not_overflow() {
+        assert_eq!(last_updated_to_system_time(u64::MAX), UNIX_EPOCH);
+    }
+}
+
+#[cfg(test)]
+mod ftp_error_mapping_tests {
+    use crate::backend::utils::transform_to_ftp_error;
+    use filefighter_api::ffs_api::ApiError;
+    use libunftp::storage::ErrorKind;
+
+    fn maps_to(variant: fn(String) -> ApiError, expected: ErrorKind) {
+        let error = variant("message".to_owned());
+        assert_eq!(transform_to_ftp_error(error).kind(), expected);
+    }
+
+    #[test]
+    fn api_errors_map_to_ftp_errors() {
+        maps_to(ApiError::NotFound, ErrorKind::PermanentFileNotAvailable);
+        maps_to(ApiError::Forbidden, ErrorKind::PermissionDenied);
+        maps_to(ApiError::Unauthorized, ErrorKind::PermissionDenied);
+        maps_to(ApiError::Conflict, ErrorKind::FileNameNotAllowedError);
+        maps_to(ApiError::BadRequest, ErrorKind::FileNameNotAllowedError);
+        maps_to(
+            ApiError::PayloadTooLarge,
+            ErrorKind::ExceededStorageAllocationError,
+        );
+        maps_to(ApiError::ServiceUnavailable, ErrorKind::LocalError);
+        maps_to(ApiError::CircuitOpen, ErrorKind::TransientFileNotAvailable);
+    }
+
+    #[test]
+    fn offsets_beyond_the_end_are_reported_for_the_file() {
+        let error = ApiError::OffsetBeyondEnd { offset: 9, size: 5 };
+        assert_eq!(
+            transform_to_ftp_error(error).kind(),
+            ErrorKind::PermanentFileNotAvailable
+        );
     }
 }
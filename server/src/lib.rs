@@ -23,7 +23,6 @@
     clippy::empty_drop,
     clippy::integer_division,
     clippy::same_name_method,
-    clippy::string_to_string,
     clippy::try_err,
     clippy::wildcard_enum_match_arm
 )]
@@ -32,7 +31,15 @@
 
 mod auth;
 mod backend;
+mod metrics;
+mod shutdown;
 
 // reexports
-pub use auth::authenticator::FileFighterAuthenticator;
-pub use backend::storage_backend::FileFighter;
+pub use auth::{authenticator::FileFighterAuthenticator, login_cache::LoginCache};
+pub use backend::{
+    checksum::{ChecksumCache, HashAlgorithm},
+    listing_cache::ListingCache,
+    metadata::GroupMapping,
+    storage_backend::{FileFighter, RenamePolicy, RmdPolicy},
+};
+pub use shutdown::GracefulShutdown;
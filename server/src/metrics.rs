@@ -0,0 +1,23 @@
+use prometheus::{register_int_counter, register_int_counter_vec, IntCounter, IntCounterVec};
+use std::sync::LazyLock;
+
+/// Logins by `result`: `success`, `disabled` or `failure`
+#[allow(clippy::unwrap_used)]
+pub static LOGINS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "ftp_fighter_logins_total",
+        "Logins handled by the FileFighter authenticator.",
+        &["result"]
+    )
+    .unwrap()
+});
+
+/// Uploads whose stored size didn't match the bytes received from the client
+#[allow(clippy::unwrap_used)]
+pub static CORRUPT_UPLOADS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "ftp_fighter_corrupt_uploads_total",
+        "Uploads where the stored file didn't match the received bytes."
+    )
+    .unwrap()
+});
@@ -0,0 +1,104 @@
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    sync::Notify,
+};
+use tracing::info;
+
+/// Shared state for draining the server before it shuts down.
+///
+/// Once shutdown began no new logins and transfers are accepted, while running transfers
+/// may finish.
+#[derive(Debug, Default)]
+pub struct GracefulShutdown {
+    shutting_down: AtomicBool,
+    active_transfers: AtomicUsize,
+    transfer_finished: Notify,
+}
+
+/// Marks a transfer as running until it is dropped
+#[derive(Debug)]
+pub struct TransferGuard {
+    shutdown: Arc<GracefulShutdown>,
+}
+
+/// Reader of a download that keeps its transfer running until it is dropped
+pub struct TrackedReader<R> {
+    inner: R,
+    _guard: TransferGuard,
+}
+
+impl<R> TrackedReader<R> {
+    pub const fn new(inner: R, guard: TransferGuard) -> Self {
+        Self {
+            inner,
+            _guard: guard,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for TrackedReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl GracefulShutdown {
+    pub fn begin(&self) {
+        info!(
+            "Shutting down, waiting for {} active transfers",
+            self.active_transfers.load(Ordering::SeqCst)
+        );
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Register a new transfer, `None` if the server is shutting down
+    pub fn start_transfer(self: &Arc<Self>) -> Option<TransferGuard> {
+        self.active_transfers.fetch_add(1, Ordering::SeqCst);
+        if self.is_shutting_down() {
+            self.finish_transfer();
+            return None;
+        }
+
+        Some(TransferGuard {
+            shutdown: Arc::clone(self),
+        })
+    }
+
+    /// Wait until no transfers are running anymore
+    pub async fn transfers_finished(&self) {
+        loop {
+            let finished = self.transfer_finished.notified();
+            if self.active_transfers.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            finished.await;
+        }
+    }
+
+    fn finish_transfer(&self) {
+        self.active_transfers.fetch_sub(1, Ordering::SeqCst);
+        self.transfer_finished.notify_waiters();
+    }
+}
+
+impl Drop for TransferGuard {
+    fn drop(&mut self) {
+        self.shutdown.finish_transfer();
+    }
+}
@@ -1,13 +1,23 @@
-use clap::Parser;
-use filefighter_api::ffs_api::ApiConfig;
+use clap::{Parser, Subcommand};
+use color_eyre::{eyre::eyre, Result as EyreResult, Section};
+use filefighter_api::ffs_api::{retry::RetryPolicy, ApiConfig, ApiError, HttpClientConfig};
+use std::{net::IpAddr, path::PathBuf, time::Duration};
 use tracing::metadata::LevelFilter;
+use unftp_filefighter::{GroupMapping, RenamePolicy, RmdPolicy};
+use url::Url;
 
 /// FileFighter FTP-Service
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Used FTP-Server hostname
-    // IDEA: url validation?
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// TOML or YAML file with the configuration, env and cli args take precedence over it
+    #[arg(long, env = "FTP_SERVICE_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Ip address the FTP-Server listens on
     #[arg(short = 'n', long, env = "FTP_SERVICE_HOSTNAME", default_value_t = String::from("0.0.0.0"))]
     pub hostname: String,
 
@@ -34,14 +44,255 @@ pub struct Args {
     /// Base url of the FileHandlerService (without trailing slash) eg. http://localhost:5000
     #[arg(short, long, env = "FTP_SERVICE_FILEHANDLER_URL")]
     pub filehandler_url: String,
+
+    /// Path prefix of the FileSystemService api, appended to the backend url
+    #[arg(long, env = "FTP_SERVICE_BACKEND_PATH_PREFIX", default_value_t = String::from("/api"))]
+    pub backend_path_prefix: String,
+
+    /// Path prefix of the FileHandlerService api, appended to the filehandler url
+    #[arg(long, env = "FTP_SERVICE_FILEHANDLER_PATH_PREFIX", default_value_t = String::from("/data"))]
+    pub filehandler_path_prefix: String,
+
+    /// PEM file with the certificate chain used for FTPS
+    #[arg(long, env = "FTP_SERVICE_FTPS_CERTS_FILE", requires = "ftps_key_file")]
+    pub ftps_certs_file: Option<PathBuf>,
+
+    /// PEM file with the private key used for FTPS
+    #[arg(long, env = "FTP_SERVICE_FTPS_KEY_FILE", requires = "ftps_certs_file")]
+    pub ftps_key_file: Option<PathBuf>,
+
+    /// Refuse clients that don't use TLS on both the control and data channel
+    #[arg(long, env = "FTP_SERVICE_FTPS_REQUIRED", requires = "ftps_certs_file")]
+    pub ftps_required: bool,
+
+    /// Port of the HTTP listener serving /metrics, /healthz and /readyz (disabled if not set)
+    #[arg(long, env = "FTP_SERVICE_MONITORING_PORT", value_parser = clap::value_parser!(u16).range(1..))]
+    pub monitoring_port: Option<u16>,
+
+    /// Seconds a successful login is reused for new sessions of the same user (0 disables caching)
+    #[arg(long, env = "FTP_SERVICE_LOGIN_CACHE_TTL", default_value_t = 300)]
+    pub login_cache_ttl: u64,
+
+    /// Maximum number of cached logins
+    #[arg(long, env = "FTP_SERVICE_LOGIN_CACHE_CAPACITY", default_value_t = 1000)]
+    pub login_cache_capacity: u64,
+
+    /// Seconds folder listings and inodes are reused within and across sessions of a user (0 disables caching)
+    #[arg(long, env = "FTP_SERVICE_LISTING_CACHE_TTL", default_value_t = 5)]
+    pub listing_cache_ttl: u64,
+
+    /// Maximum number of cached folder listings and inodes, each
+    #[arg(
+        long,
+        env = "FTP_SERVICE_LISTING_CACHE_CAPACITY",
+        default_value_t = 10000
+    )]
+    pub listing_cache_capacity: u64,
+
+    /// Maximum idle connections kept open per backend host
+    #[arg(long, env = "FTP_SERVICE_HTTP_POOL_MAX_IDLE", default_value_t = 32)]
+    pub http_pool_max_idle: usize,
+
+    /// Seconds an idle backend connection is kept in the pool
+    #[arg(long, env = "FTP_SERVICE_HTTP_POOL_IDLE_TIMEOUT", default_value_t = 90)]
+    pub http_pool_idle_timeout: u64,
+
+    /// Interval in seconds for TCP keep-alive probes on backend connections
+    #[arg(long, env = "FTP_SERVICE_HTTP_KEEPALIVE", default_value_t = 60)]
+    pub http_keepalive: u64,
+
+    /// Seconds to wait for a connection to a backend
+    #[arg(long, env = "FTP_SERVICE_HTTP_CONNECT_TIMEOUT", default_value_t = 10)]
+    pub http_connect_timeout: u64,
+
+    /// Seconds a backend request may take (file transfers are excluded)
+    #[arg(long, env = "FTP_SERVICE_HTTP_REQUEST_TIMEOUT", default_value_t = 30)]
+    pub http_request_timeout: u64,
+
+    /// Talk HTTP/2 to the backends without negotiating it first
+    #[arg(long, env = "FTP_SERVICE_HTTP2_PRIOR_KNOWLEDGE")]
+    pub http2_prior_knowledge: bool,
+
+    /// Attempts for backend requests that can safely be repeated (1 disables retries)
+    #[arg(long, env = "FTP_SERVICE_HTTP_RETRY_MAX_ATTEMPTS", default_value_t = 3)]
+    pub http_retry_max_attempts: u32,
+
+    /// Milliseconds before the first retry, doubled for every further one
+    #[arg(
+        long,
+        env = "FTP_SERVICE_HTTP_RETRY_BASE_DELAY_MS",
+        default_value_t = 100
+    )]
+    pub http_retry_base_delay_ms: u64,
+
+    /// Maximum milliseconds between retries, longer Retry-After values are not waited for
+    #[arg(
+        long,
+        env = "FTP_SERVICE_HTTP_RETRY_MAX_DELAY_MS",
+        default_value_t = 2000
+    )]
+    pub http_retry_max_delay_ms: u64,
+
+    /// Failed backend requests in a row after which requests fail fast (0 disables this)
+    #[arg(
+        long,
+        env = "FTP_SERVICE_HTTP_CIRCUIT_BREAKER_THRESHOLD",
+        default_value_t = 5
+    )]
+    pub http_circuit_breaker_threshold: u32,
+
+    /// Seconds requests to a failing backend fail fast before it is tried again
+    #[arg(
+        long,
+        env = "FTP_SERVICE_HTTP_CIRCUIT_BREAKER_COOLDOWN",
+        default_value_t = 10
+    )]
+    pub http_circuit_breaker_cooldown: u64,
+
+    /// Maximum number of cached file checksums (SITE MD5)
+    #[arg(
+        long,
+        env = "FTP_SERVICE_CHECKSUM_CACHE_CAPACITY",
+        default_value_t = 10000
+    )]
+    pub checksum_cache_capacity: u64,
+
+    /// Delete uploads whose stored size doesn't match the bytes received from the client
+    #[arg(long, env = "FTP_SERVICE_DELETE_CORRUPT_UPLOADS")]
+    pub delete_corrupt_uploads: bool,
+
+    /// Upload to a hidden temporary file and rename it to the target name once complete
+    #[arg(long, env = "FTP_SERVICE_SAFE_UPLOADS")]
+    pub safe_uploads: bool,
+
+    /// Bytes every user may store, uploads beyond it are refused with 552 (unlimited if not set)
+    #[arg(long, env = "FTP_SERVICE_USER_QUOTA")]
+    pub user_quota: Option<u64>,
+
+    /// What RMD does with folders that aren't empty: refuse or recursive
+    #[arg(long, env = "FTP_SERVICE_RMD_POLICY", default_value_t = RmdPolicy::Refuse)]
+    pub rmd_policy: RmdPolicy,
+
+    /// What RNTO does if the target already exists: refuse or overwrite (files only)
+    #[arg(long, env = "FTP_SERVICE_RENAME_POLICY", default_value_t = RenamePolicy::Refuse)]
+    pub rename_policy: RenamePolicy,
+
+    /// Group shown in listings for everything below a folder, eg. /Shared=team (can be repeated)
+    #[arg(
+        long = "group-mapping",
+        env = "FTP_SERVICE_GROUP_MAPPINGS",
+        value_delimiter = ','
+    )]
+    pub group_mappings: Vec<GroupMapping>,
+
+    /// Seconds running transfers may take to finish after SIGTERM or SIGINT
+    #[arg(long, env = "FTP_SERVICE_SHUTDOWN_GRACE_PERIOD", default_value_t = 30)]
+    pub shutdown_grace_period: u64,
+}
+
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Validate the configuration and print the effective values with secrets redacted
+    CheckConfig,
+}
+
+impl Args {
+    /// Check the values clap can't validate on its own
+    pub fn validate(&self) -> EyreResult<()> {
+        self.hostname
+            .parse::<IpAddr>()
+            .map_err(|err| eyre!("Invalid hostname '{}': {}", self.hostname, err))
+            .suggestion("Use an ip address like 0.0.0.0 or 127.0.0.1")?;
+
+        validate_base_url("backend-url", &self.backend_url)?;
+        validate_base_url("filehandler-url", &self.filehandler_url)?;
+        validate_path_prefix("backend-path-prefix", &self.backend_path_prefix)?;
+        validate_path_prefix("filehandler-path-prefix", &self.filehandler_path_prefix)?;
+
+        if self.passive_start_port > self.passive_end_port {
+            return Err(eyre!(
+                "The passive port range {}-{} is empty",
+                self.passive_start_port,
+                self.passive_end_port
+            ))
+            .suggestion("passive-start-port must not be greater than passive-end-port");
+        }
+
+        if self.http_retry_max_attempts == 0 {
+            return Err(eyre!("http-retry-max-attempts must be at least 1"))
+                .suggestion("Use 1 to disable retries");
+        }
+
+        Ok(())
+    }
+}
+
+/// Base urls need a http(s) scheme and a host, and must not end with a slash
+fn validate_base_url(name: &str, url: &str) -> EyreResult<()> {
+    let parsed = Url::parse(url)
+        .map_err(|err| eyre!("Invalid {} '{}': {}", name, url, err))
+        .suggestion("Use a url like http://localhost:8080")?;
+
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(eyre!(
+            "Unsupported scheme '{}' in {} '{}'",
+            parsed.scheme(),
+            name,
+            url
+        ))
+        .suggestion("Only http and https are supported");
+    }
+    if !parsed.has_host() {
+        return Err(eyre!("The {} '{}' has no host", name, url));
+    }
+    if url.ends_with('/') {
+        return Err(eyre!("The {} '{}' ends with a slash", name, url))
+            .suggestion(format!("Use '{}' instead", url.trim_end_matches('/')));
+    }
+
+    Ok(())
+}
+
+/// Path prefixes are either empty or start but don't end with a slash
+fn validate_path_prefix(name: &str, prefix: &str) -> EyreResult<()> {
+    if prefix.is_empty() || (prefix.starts_with('/') && !prefix.ends_with('/')) {
+        Ok(())
+    } else {
+        Err(eyre!("Invalid {} '{}'", name, prefix))
+            .suggestion("Use a path like /api, without a trailing slash")
+    }
 }
 
 /// Implement conversion between config and args
-impl From<Args> for ApiConfig {
-    fn from(args: Args) -> Self {
+impl From<&Args> for HttpClientConfig {
+    fn from(args: &Args) -> Self {
         Self {
-            fss_base_url: args.backend_url,
-            fhs_base_url: args.filehandler_url,
+            pool_max_idle_per_host: args.http_pool_max_idle,
+            pool_idle_timeout: Duration::from_secs(args.http_pool_idle_timeout),
+            tcp_keepalive: Duration::from_secs(args.http_keepalive),
+            connect_timeout: Duration::from_secs(args.http_connect_timeout),
+            request_timeout: Duration::from_secs(args.http_request_timeout),
+            http2_prior_knowledge: args.http2_prior_knowledge,
+            retry: RetryPolicy {
+                max_attempts: args.http_retry_max_attempts,
+                base_delay: Duration::from_millis(args.http_retry_base_delay_ms),
+                max_delay: Duration::from_millis(args.http_retry_max_delay_ms),
+            },
+            circuit_breaker_threshold: args.http_circuit_breaker_threshold,
+            circuit_breaker_cooldown: Duration::from_secs(args.http_circuit_breaker_cooldown),
         }
     }
 }
+
+impl TryFrom<Args> for ApiConfig {
+    type Error = ApiError;
+
+    fn try_from(args: Args) -> Result<Self, Self::Error> {
+        let http_config = HttpClientConfig::from(&args);
+        Self::new(
+            args.backend_url + &args.backend_path_prefix,
+            args.filehandler_url + &args.filehandler_path_prefix,
+            &http_config,
+        )
+    }
+}
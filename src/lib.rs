@@ -1,22 +1,28 @@
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use cli::Args;
+use color_eyre::{eyre::eyre, Section};
+use config::ConfigFile;
 use dotenvy::dotenv;
 use filefighter_api::ffs_api::ApiConfig;
-use libunftp::ServerError;
-use std::{ops::Range, sync::Arc};
-use tracing::{debug, info, metadata::LevelFilter, Level};
+use libunftp::{options::Shutdown, ServerError};
+use std::{future::Future, ops::Range, path::PathBuf, sync::Arc, time::Duration};
+use tokio::sync::oneshot;
+use tracing::{debug, error, info, metadata::LevelFilter, warn, Level};
 use tracing_subscriber::{filter::Targets, fmt::time::SystemTime, prelude::*};
-use unftp_filefighter::{FileFighter, FileFighterAuthenticator};
+use unftp_filefighter::{
+    ChecksumCache, FileFighter, FileFighterAuthenticator, GracefulShutdown, ListingCache,
+    LoginCache,
+};
 
-mod cli;
+pub mod cli;
+mod config;
+mod monitoring;
 
 pub fn setup_logging(args: &Args) {
-    color_eyre::install().unwrap();
-
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::fmt::layer()
-                .with_timer(SystemTime::default()) // i think this is the default one
+                .with_timer(SystemTime) // i think this is the default one
                 .with_ansi(true),
         )
         .with(
@@ -32,37 +38,160 @@ pub fn setup_logging(args: &Args) {
         .init();
 }
 
-pub fn parse_cli_args() -> Args {
+/// Read the args from cli, env and config file and validate them
+pub fn parse_cli_args() -> color_eyre::Result<Args> {
     // read from env
     dotenv().ok();
+    // read from the config file, the cli args are parsed twice to find it
+    let config_file = Args::command()
+        .ignore_errors(true)
+        .try_get_matches()
+        .ok()
+        .and_then(|matches| matches.get_one::<PathBuf>("config").cloned());
+    if let Some(config_file) = config_file {
+        ConfigFile::read(&config_file)
+            .map_err(|err| eyre!(err))
+            .suggestion("Check the file given with --config or FTP_SERVICE_CONFIG")?
+            .apply_to_env();
+    }
+
+    let args = Args::parse();
+    args.validate()?;
+    Ok(args)
+}
+
+/// Resolves once the process receives SIGTERM or SIGINT
+fn shutdown_signal() -> oneshot::Receiver<()> {
+    let (sender, receiver) = oneshot::channel();
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            match signal(SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    tokio::select! {
+                        _ = tokio::signal::ctrl_c() => {},
+                        _ = terminate.recv() => {},
+                    }
+                }
+                Err(err) => {
+                    error!("Could not listen for SIGTERM: {}", err);
+                    tokio::signal::ctrl_c().await.ok();
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        tokio::signal::ctrl_c().await.ok();
+
+        sender.send(()).ok();
+    });
+    receiver
+}
 
-    let mut args = Args::parse();
-    // add missing uri components
-    args.backend_url.push_str("/api");
-    args.filehandler_url.push_str("/data");
-    args
+/// Print the effective configuration, after checking that the backend client can be built
+pub fn check_config(args: &Args) -> color_eyre::Result<()> {
+    ApiConfig::try_from(args.clone())?;
+    println!("{}", toml::to_string_pretty(&ConfigFile::redacted(args))?);
+    Ok(())
 }
 
 pub async fn start_ftp_service(args: Args) -> Result<(), ServerError> {
-    let api_config: ApiConfig = args.clone().into();
+    start_ftp_service_until(args, shutdown_signal()).await
+}
+
+/// Run the ftp service until `shutdown` resolves, then let running transfers finish
+pub async fn start_ftp_service_until<S>(args: Args, shutdown: S) -> Result<(), ServerError>
+where
+    S: Future + Send + Sync + 'static,
+{
+    let api_config = ApiConfig::try_from(args.clone()).map_err(std::io::Error::other)?;
     let api_config_clone = api_config.clone();
 
     info!("Starting FTP Server...");
     debug!("Config: {:#?}", args);
 
-    libunftp::Server::with_authenticator(
+    let login_cache = (args.login_cache_ttl != 0).then(|| {
+        Arc::new(LoginCache::new(
+            args.login_cache_capacity,
+            Duration::from_secs(args.login_cache_ttl),
+        ))
+    });
+    let login_cache_clone = login_cache.clone();
+
+    let group_mappings = Arc::new(args.group_mappings.clone());
+    let checksum_cache = Arc::new(ChecksumCache::new(args.checksum_cache_capacity));
+    let listing_cache = (args.listing_cache_ttl != 0).then(|| {
+        Arc::new(ListingCache::new(
+            args.listing_cache_capacity,
+            Duration::from_secs(args.listing_cache_ttl),
+        ))
+    });
+    let graceful_shutdown = Arc::new(GracefulShutdown::default());
+    let graceful_shutdown_clone = graceful_shutdown.clone();
+    let shutdown_indicator = {
+        let graceful_shutdown = graceful_shutdown.clone();
+        let grace_period = Duration::from_secs(args.shutdown_grace_period);
+        async move {
+            shutdown.await;
+            graceful_shutdown.begin();
+            if tokio::time::timeout(grace_period, graceful_shutdown.transfers_finished())
+                .await
+                .is_err()
+            {
+                warn!("Transfers did not finish within {:?}", grace_period);
+            }
+            info!("Closing all connections");
+            Shutdown::new().grace_period(Duration::from_secs(1))
+        }
+    };
+
+    if let Some(monitoring_port) = args.monitoring_port {
+        let address = format!("{}:{}", args.hostname, monitoring_port).parse()?;
+        let api_config = api_config.clone();
+        tokio::spawn(async move {
+            if let Err(err) = monitoring::serve(address, api_config).await {
+                error!("Monitoring endpoints failed: {}", err);
+            }
+        });
+    }
+
+    let mut server = libunftp::Server::with_authenticator(
         Box::new(move || FileFighter {
             api_config: api_config.clone(),
+            login_cache: login_cache.clone(),
+            shutdown: graceful_shutdown.clone(),
+            group_mappings: group_mappings.clone(),
+            checksum_cache: checksum_cache.clone(),
+            listing_cache: listing_cache.clone(),
+            delete_corrupt_uploads: args.delete_corrupt_uploads,
+            safe_uploads: args.safe_uploads,
+            rmd_policy: args.rmd_policy,
+            rename_policy: args.rename_policy,
+            user_quota: args.user_quota,
         }),
         Arc::new(FileFighterAuthenticator {
             api_config: api_config_clone,
+            login_cache: login_cache_clone,
+            shutdown: graceful_shutdown_clone,
         }),
     )
     .greeting("FileFighter FTP server")
+    .metrics()
+    .shutdown_indicator(shutdown_indicator)
     .passive_ports(Range {
         start: args.passive_start_port,
         end: args.passive_end_port,
-    })
-    .listen(format!("{}:{}", args.hostname, args.port))
-    .await
+    });
+
+    if let (Some(certs_file), Some(key_file)) = (args.ftps_certs_file, args.ftps_key_file) {
+        info!("Enabling FTPS (required: {})", args.ftps_required);
+        server = server
+            .ftps(certs_file, key_file)
+            .ftps_required(args.ftps_required, args.ftps_required);
+    }
+
+    server
+        .listen(format!("{}:{}", args.hostname, args.port))
+        .await
 }
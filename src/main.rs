@@ -1,7 +1,15 @@
+use ftp_fighter::cli::Command;
+
 #[tokio::main]
-pub async fn main() -> Result<(), libunftp::ServerError> {
-    let args = ftp_fighter::parse_cli_args();
+pub async fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+    let args = ftp_fighter::parse_cli_args()?;
+
+    if args.command == Some(Command::CheckConfig) {
+        return ftp_fighter::check_config(&args);
+    }
 
     ftp_fighter::setup_logging(&args);
-    ftp_fighter::start_ftp_service(args).await
+    ftp_fighter::start_ftp_service(args).await?;
+    Ok(())
 }
@@ -0,0 +1,104 @@
+use filefighter_api::ffs_api::{
+    endpoints::{check_filehandlerservice_health, check_filesystemservice_health},
+    ApiConfig,
+};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use prometheus::{Encoder, TextEncoder};
+use serde_json::{json, Value};
+use std::{convert::Infallible, net::SocketAddr};
+use tracing::{debug, info, warn};
+
+/// Serve the monitoring endpoints
+///
+/// - `/metrics`: prometheus metrics of libunftp and the FileFighter backends
+/// - `/healthz`: the process is alive
+/// - `/readyz`: both backends are reachable, with the status of each as json
+pub async fn serve(address: SocketAddr, api_config: ApiConfig) -> Result<(), hyper::Error> {
+    let make_service = make_service_fn(move |_| {
+        let api_config = api_config.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle_request(request, api_config.clone())
+            }))
+        }
+    });
+
+    info!("Serving monitoring endpoints on {}", address);
+    Server::try_bind(&address)?.serve(make_service).await
+}
+
+async fn handle_request(
+    request: Request<Body>,
+    api_config: ApiConfig,
+) -> Result<Response<Body>, Infallible> {
+    Ok(match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => metrics(),
+        (&Method::GET, "/healthz") => json_response(StatusCode::OK, &json!({ "status": "up" })),
+        (&Method::GET, "/readyz") => readiness(&api_config).await,
+        _ => response(StatusCode::NOT_FOUND, "text/plain", "Not Found"),
+    })
+}
+
+fn metrics() -> Response<Body> {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+
+    match encoder.encode(&prometheus::gather(), &mut buffer) {
+        Ok(()) => response(StatusCode::OK, encoder.format_type(), buffer),
+        Err(err) => {
+            warn!("Could not encode metrics: {}", err);
+            response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "text/plain",
+                "Could not encode metrics",
+            )
+        }
+    }
+}
+
+async fn readiness(api_config: &ApiConfig) -> Response<Body> {
+    let (fss, fhs) = tokio::join!(
+        check_filesystemservice_health(api_config),
+        check_filehandlerservice_health(api_config)
+    );
+    let ready = fss.is_ok() && fhs.is_ok();
+
+    let dependency = |result: filefighter_api::ffs_api::Result<()>| match result {
+        Ok(()) => json!({ "status": "up" }),
+        Err(err) => {
+            debug!("Backend not ready: {}", err);
+            json!({ "status": "down", "error": err.to_string() })
+        }
+    };
+    let body = json!({
+        "status": if ready { "up" } else { "down" },
+        "dependencies": {
+            "fileSystemService": dependency(fss),
+            "fileHandlerService": dependency(fhs),
+        },
+    });
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    json_response(status, &body)
+}
+
+fn json_response(status: StatusCode, body: &Value) -> Response<Body> {
+    response(status, "application/json", body.to_string())
+}
+
+fn response(status: StatusCode, content_type: &str, body: impl Into<Body>) -> Response<Body> {
+    let mut response = Response::new(body.into());
+    *response.status_mut() = status;
+    if let Ok(content_type) = content_type.parse() {
+        response.headers_mut().insert(CONTENT_TYPE, content_type);
+    }
+    response
+}
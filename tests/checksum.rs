@@ -0,0 +1,129 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::{start_server, PASSWORD, USERNAME};
+use std::{
+    io::{BufRead, BufReader, Cursor, Write},
+    net::{SocketAddr, TcpStream},
+};
+
+/// Send control commands and return the reply to the last one
+///
+/// suppaftp only reports the status code of custom commands, not the reply text.
+fn control_session(address: SocketAddr, commands: &[&str]) -> String {
+    let stream = TcpStream::connect(address).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = stream;
+    let mut reply = String::new();
+    reader.read_line(&mut reply).unwrap();
+
+    let login = [format!("USER {USERNAME}"), format!("PASS {PASSWORD}")];
+    let commands = commands.iter().map(|command| (*command).to_owned());
+    for command in login.into_iter().chain(commands) {
+        write!(writer, "{command}\r\n").unwrap();
+        reply.clear();
+        reader.read_line(&mut reply).unwrap();
+    }
+    reply.trim_end().to_owned()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn site_md5_returns_cached_checksums() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file(
+        "/Home/fox.txt",
+        b"The quick brown fox jumps over the lazy dog",
+    );
+    let address = server.ftp_address;
+
+    let replies = tokio::task::spawn_blocking(move || {
+        let command = ["SITE MD5 /Home/fox.txt"];
+        [
+            control_session(address, &command),
+            control_session(address, &command),
+        ]
+    })
+    .await
+    .unwrap();
+
+    for reply in replies {
+        assert_eq!(
+            reply,
+            "213 9e107d9d372bb6826bd81d3542a419d6    /Home/fox.txt"
+        );
+    }
+    assert_eq!(server.downloads(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changed_files_get_a_new_checksum() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/file.txt", b"");
+    let address = server.ftp_address;
+    const COMMAND: [&str; 1] = ["SITE MD5 /Home/file.txt"];
+
+    let before = tokio::task::spawn_blocking(move || control_session(address, &COMMAND))
+        .await
+        .unwrap();
+    server.add_file("/Home/file.txt", b"changed");
+    let after = tokio::task::spawn_blocking(move || control_session(address, &COMMAND))
+        .await
+        .unwrap();
+
+    assert!(
+        before.contains("d41d8cd98f00b204e9800998ecf8427e"),
+        "{before}"
+    );
+    assert!(
+        after.contains("8977dfac2f8e04cb96e66882235f5aba"),
+        "{after}"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn overwritten_files_get_a_new_checksum() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    let address = server.ftp_address;
+    const COMMAND: [&str; 1] = ["SITE MD5 /Home/file.txt"];
+
+    // both uploads keep the id and usually happen within the same second
+    let checksums = server
+        .session(move |ftp| {
+            let mut checksums = Vec::new();
+            for contents in [&b""[..], b"changed"] {
+                ftp.put_file("/Home/file.txt", &mut Cursor::new(contents))
+                    .unwrap();
+                checksums.push(control_session(address, &COMMAND));
+            }
+            checksums
+        })
+        .await;
+
+    assert!(
+        checksums[0].contains("d41d8cd98f00b204e9800998ecf8427e"),
+        "{}",
+        checksums[0]
+    );
+    assert!(
+        checksums[1].contains("8977dfac2f8e04cb96e66882235f5aba"),
+        "{}",
+        checksums[1]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn folders_have_no_checksum() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    let address = server.ftp_address;
+
+    let reply = tokio::task::spawn_blocking(move || control_session(address, &["SITE MD5 /Home"]))
+        .await
+        .unwrap();
+
+    assert!(reply.starts_with("550"), "{reply}");
+}
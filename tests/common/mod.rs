@@ -0,0 +1,805 @@
+//! In-process fake of the FileSystemService and FileHandlerService.
+//!
+//! The fake keeps an in-memory inode tree and answers with the same JSON shapes as the
+//! real services, so the FTP server can be driven end to end without a FileFighter instance.
+#![allow(dead_code, clippy::unwrap_used)]
+
+use axum::{
+    body::StreamBody,
+    extract::{Multipart, Path as UrlPath, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::Parser;
+use filefighter_api::ffs_api::models::{
+    contents_resource::ContentsResource, folder_creation_resource::FolderCreationResource,
+    inode_resource::InodeResource, inode_timestamp_update_ressource::InodeTimestampUpdateRessource,
+    move_resource::MoveResource, rename_resource::RenameResource, user_resource::UserResource,
+};
+use ftp_fighter::cli::Args;
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    path::Path,
+    sync::{
+        atomic::{AtomicU16, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{sync::oneshot, task::JoinHandle};
+
+pub const USERNAME: &str = "user";
+pub const PASSWORD: &str = "password";
+pub const USER_ID: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct Inode {
+    pub id: u64,
+    /// `None` for folders
+    pub contents: Option<Vec<u8>>,
+    pub last_updated: u64,
+    /// Content type of the uploaded part, `None` for folders and files added by tests
+    pub mime_type: Option<String>,
+}
+
+/// The in-memory filesystem, keyed by absolute path
+#[derive(Debug)]
+pub struct MockState {
+    pub inodes: BTreeMap<String, Inode>,
+    next_id: u64,
+    /// The only token accepted right now
+    pub token: String,
+    /// Number of calls to `/user/authenticate` with correct credentials
+    pub authentications: usize,
+    /// Number of authorized calls to `/data/download`
+    pub downloads: usize,
+    /// Number of authorized calls to `/user/info`
+    pub user_infos: usize,
+    /// Number of authorized calls to `/filesystem/info` and `/filesystem/contents`
+    pub lookups: usize,
+    /// Answer this many of the next lookups with 503 and `Retry-After: 0`
+    pub unavailable_lookups: usize,
+    /// Drop the last byte of every upload, like a flaky proxy would
+    pub truncate_uploads: bool,
+    /// Answer downloads with the whole file even if a `Range` was requested
+    pub ignore_ranges: bool,
+    /// Answer `/filesystem/rename` with an internal server error
+    pub fail_renames: bool,
+    /// Answer `/filesystem/move` with an internal server error
+    pub fail_moves: bool,
+    /// Answer `/data/delete` with an internal server error
+    pub fail_deletes: bool,
+    /// Privileges reported by `/user/info`
+    pub privileges: String,
+    /// Whether `/data/health` reports the FileHandlerService as healthy
+    pub fhs_healthy: bool,
+}
+
+impl MockState {
+    fn new() -> Self {
+        let mut inodes = BTreeMap::new();
+        inodes.insert(
+            "/".to_owned(),
+            Inode {
+                id: 0,
+                contents: None,
+                last_updated: 0,
+                mime_type: None,
+            },
+        );
+        Self {
+            inodes,
+            next_id: 1,
+            token: String::new(),
+            authentications: 0,
+            downloads: 0,
+            user_infos: 0,
+            lookups: 0,
+            unavailable_lookups: 0,
+            truncate_uploads: false,
+            ignore_ranges: false,
+            fail_renames: false,
+            fail_moves: false,
+            fail_deletes: false,
+            privileges: "NORMAL".to_owned(),
+            fhs_healthy: true,
+        }
+    }
+
+    fn insert(&mut self, path: String, contents: Option<Vec<u8>>) -> InodeResource {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.inodes.insert(
+            path.clone(),
+            Inode {
+                id,
+                contents,
+                last_updated: 1_600_000_000,
+                mime_type: None,
+            },
+        );
+        self.resource(&path).unwrap()
+    }
+
+    fn resource(&self, path: &str) -> Option<InodeResource> {
+        let inode = self.inodes.get(path)?;
+        Some(InodeResource {
+            id: inode.id.to_string(),
+            last_updated: inode.last_updated,
+            last_updated_by: user(),
+            mime_type: inode.contents.as_ref().map(|_| {
+                inode
+                    .mime_type
+                    .clone()
+                    .unwrap_or_else(|| "application/octet-stream".to_owned())
+            }),
+            name: Path::new(path)
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+            path: path.to_owned(),
+            size: self.size(path),
+        })
+    }
+
+    /// Size of a file, or of everything in a folder
+    fn size(&self, path: &str) -> u64 {
+        self.subtree(path)
+            .iter()
+            .filter_map(|path| self.inodes.get(path)?.contents.as_ref())
+            .map(|contents| contents.len() as u64)
+            .sum()
+    }
+
+    fn children(&self, path: &str) -> Vec<InodeResource> {
+        self.inodes
+            .keys()
+            .filter(|child| *child != "/" && parent_of(child) == path)
+            .filter_map(|child| self.resource(child))
+            .collect()
+    }
+
+    /// Paths of the inode and everything below it
+    fn subtree(&self, path: &str) -> Vec<String> {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        self.inodes
+            .keys()
+            .filter(|other| *other == path || other.starts_with(&prefix))
+            .cloned()
+            .collect()
+    }
+
+    fn relocate(&mut self, from: &str, to: &str) {
+        for old in self.subtree(from) {
+            let inode = self.inodes.remove(&old).unwrap();
+            let new = format!("{}{}", to, &old[from.len()..]);
+            self.inodes.insert(new, inode);
+        }
+    }
+}
+
+pub type SharedState = Arc<Mutex<MockState>>;
+
+fn user() -> UserResource {
+    UserResource {
+        id: USER_ID,
+        privileges: "NORMAL".to_owned(),
+        username: USERNAME.to_owned(),
+    }
+}
+
+fn parent_of(path: &str) -> String {
+    Path::new(path)
+        .parent()
+        .map_or_else(|| "/".to_owned(), |p| p.to_string_lossy().into_owned())
+}
+
+fn join(parent: &str, name: &str) -> String {
+    format!("{}/{}", parent.trim_end_matches('/'), name)
+}
+
+/// Seconds since the epoch, the resolution of `lastUpdated`
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    let body = serde_json::json!({
+        "message": message,
+        "status": status.canonical_reason(),
+    });
+    (status, Json(body)).into_response()
+}
+
+/// An overloaded backend that asks to be retried right away
+fn unavailable() -> Response {
+    let mut response = error(StatusCode::SERVICE_UNAVAILABLE, "Try again");
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, header::HeaderValue::from_static("0"));
+    response
+}
+
+fn authorized(state: &MockState, headers: &HeaderMap) -> bool {
+    !state.token.is_empty()
+        && headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value == format!("Bearer {}", state.token))
+}
+
+fn ff_path(headers: &HeaderMap) -> String {
+    headers
+        .get("X-FF-PATH")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("/")
+        .to_owned()
+}
+
+fn has_token(state: &MockState, query: &HashMap<String, String>) -> bool {
+    !state.token.is_empty()
+        && query
+            .get("token")
+            .is_some_and(|token| *token == state.token)
+}
+
+async fn authenticate(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    let expected_password =
+        sha256::digest(format!("{PASSWORD}FileFighterWithSomeSalt")).to_uppercase();
+    let expected = format!(
+        "Basic {}",
+        STANDARD.encode(format!("{USERNAME}:{expected_password}"))
+    );
+
+    match headers.get(header::AUTHORIZATION) {
+        Some(value) if value == expected.as_str() => {
+            let mut state = state.lock().unwrap();
+            state.authentications += 1;
+            state.token = format!("mock-token-{}", state.authentications);
+            (
+                StatusCode::CREATED,
+                [(header::SET_COOKIE, format!("token={}; Path=/", state.token))],
+                Json(serde_json::json!({ "token": state.token })),
+            )
+                .into_response()
+        }
+        _ => error(StatusCode::UNAUTHORIZED, "Bad credentials"),
+    }
+}
+
+async fn user_info(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    let mut state = state.lock().unwrap();
+    if !authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    state.user_infos += 1;
+    Json(UserResource {
+        privileges: state.privileges.clone(),
+        ..user()
+    })
+    .into_response()
+}
+
+async fn fss_health() -> Response {
+    Json(serde_json::json!({ "deployment": "mock" })).into_response()
+}
+
+async fn fhs_health(State(state): State<SharedState>) -> Response {
+    if state.lock().unwrap().fhs_healthy {
+        Json(serde_json::json!({ "deployment": "mock" })).into_response()
+    } else {
+        error(StatusCode::SERVICE_UNAVAILABLE, "Storage unavailable")
+    }
+}
+
+async fn inode_info(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    let mut state = state.lock().unwrap();
+    if !authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    state.lookups += 1;
+    if state.unavailable_lookups > 0 {
+        state.unavailable_lookups -= 1;
+        return unavailable();
+    }
+    match state.resource(&ff_path(&headers)) {
+        Some(inode) => Json(inode).into_response(),
+        None => error(StatusCode::NOT_FOUND, "Inode not found"),
+    }
+}
+
+async fn contents(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    let mut state = state.lock().unwrap();
+    if !authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    state.lookups += 1;
+    if state.unavailable_lookups > 0 {
+        state.unavailable_lookups -= 1;
+        return unavailable();
+    }
+    let path = ff_path(&headers);
+    match state.inodes.get(&path) {
+        Some(inode) if inode.contents.is_none() => Json(ContentsResource {
+            inodes: state.children(&path),
+            owner: user(),
+        })
+        .into_response(),
+        _ => error(StatusCode::NOT_FOUND, "Folder not found"),
+    }
+}
+
+async fn create_folder(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(body): Json<FolderCreationResource>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if !authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    let path = join(&body.parent_path, &body.name);
+    if state.inodes.contains_key(&path) {
+        return error(StatusCode::CONFLICT, "Inode already exists");
+    }
+    if !state.inodes.contains_key(&body.parent_path) {
+        return error(StatusCode::NOT_FOUND, "Parent not found");
+    }
+    (StatusCode::CREATED, Json(state.insert(path, None))).into_response()
+}
+
+async fn rename(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(body): Json<RenameResource>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if !authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    if state.fail_renames {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, "Rename failed");
+    }
+    let new_path = join(&parent_of(&body.path), &body.new_name);
+    if !state.inodes.contains_key(&body.path) {
+        return error(StatusCode::NOT_FOUND, "Inode not found");
+    }
+    if state.inodes.contains_key(&new_path) {
+        return error(StatusCode::CONFLICT, "Inode already exists");
+    }
+    state.relocate(&body.path, &new_path);
+    Json(state.resource(&new_path).unwrap()).into_response()
+}
+
+async fn move_inode(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(body): Json<MoveResource>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if !authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    if state.fail_moves {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, "Move failed");
+    }
+    let name = Path::new(&body.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let new_path = join(&body.new_path, &name);
+    if !state.inodes.contains_key(&body.path) || !state.inodes.contains_key(&body.new_path) {
+        return error(StatusCode::NOT_FOUND, "Inode not found");
+    }
+    if state.inodes.contains_key(&new_path) {
+        return error(StatusCode::CONFLICT, "Inode already exists");
+    }
+    state.relocate(&body.path, &new_path);
+    Json(state.resource(&new_path).unwrap()).into_response()
+}
+
+async fn timestamp(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(body): Json<InodeTimestampUpdateRessource>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if !authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    match state.inodes.get_mut(&body.path) {
+        Some(inode) => {
+            inode.last_updated = u64::try_from(body.timestamp).unwrap_or_default();
+            Json(state.resource(&body.path).unwrap()).into_response()
+        }
+        None => error(StatusCode::NOT_FOUND, "Inode not found"),
+    }
+}
+
+async fn upload(
+    State(state): State<SharedState>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Response {
+    if !has_token(&state.lock().unwrap(), &query) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    let parent = headers
+        .get("X-FF-PARENT-PATH")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("/")
+        .to_owned();
+    let name = headers
+        .get("X-FF-RELATIVE-PATH")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+
+    let path = join(&parent, &name);
+    {
+        let mut state = state.lock().unwrap();
+        if !state.inodes.contains_key(&parent) {
+            return error(StatusCode::NOT_FOUND, "Parent not found");
+        }
+        // like the FileHandlerService, the target is replaced as soon as the upload starts
+        match state.inodes.get_mut(&path) {
+            Some(existing) if existing.contents.is_some() => existing.contents = Some(Vec::new()),
+            Some(_) => return error(StatusCode::CONFLICT, "Folder exists with that name"),
+            None => {
+                state.insert(path.clone(), Some(Vec::new()));
+            }
+        }
+        if let Some(inode) = state.inodes.get_mut(&path) {
+            inode.last_updated = now();
+        }
+    }
+
+    while let Ok(Some(mut field)) = multipart.next_field().await {
+        let mime_type = field.content_type().map(ToOwned::to_owned);
+        if let Some(inode) = state.lock().unwrap().inodes.get_mut(&path) {
+            inode.mime_type = mime_type;
+        }
+        while let Ok(Some(chunk)) = field.chunk().await {
+            let mut state = state.lock().unwrap();
+            if let Some(contents) = state
+                .inodes
+                .get_mut(&path)
+                .and_then(|inode| inode.contents.as_mut())
+            {
+                contents.extend_from_slice(&chunk);
+            }
+        }
+    }
+
+    let mut state = state.lock().unwrap();
+    let truncate = state.truncate_uploads;
+    let Some(inode) = state.inodes.get_mut(&path) else {
+        return error(StatusCode::NOT_FOUND, "File was removed during the upload");
+    };
+    if truncate {
+        inode.contents.as_mut().map(Vec::pop);
+    }
+    Json(vec![state.resource(&path).unwrap()]).into_response()
+}
+
+async fn download(
+    State(shared): State<SharedState>,
+    UrlPath(path): UrlPath<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+) -> Response {
+    let mut state = shared.lock().unwrap();
+    if !has_token(&state, &query) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    state.downloads += 1;
+    let path = format!("/{path}");
+    let Some(size) = state
+        .inodes
+        .get(&path)
+        .and_then(|inode| inode.contents.as_ref())
+        .map(Vec::len)
+    else {
+        return error(StatusCode::NOT_FOUND, "File not found");
+    };
+    let ignore_ranges = state.ignore_ranges;
+    drop(state);
+
+    let range_start = headers
+        .get(header::RANGE)
+        .filter(|_| !ignore_ranges)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("bytes="))
+        .and_then(|value| value.strip_suffix('-'))
+        .and_then(|value| value.parse::<usize>().ok());
+
+    let (status, start) = match range_start {
+        Some(start) if start < size => (StatusCode::PARTIAL_CONTENT, start),
+        Some(_) => {
+            let content_range = format!("bytes */{size}");
+            return (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, content_range)],
+            )
+                .into_response();
+        }
+        None => (StatusCode::OK, 0),
+    };
+    (status, StreamBody::new(read_lazily(shared, path, start))).into_response()
+}
+
+/// Stream the file in small chunks read from the current state, like a file server reading
+/// from disk, so changes made while the download is running show up in it
+fn read_lazily(
+    state: SharedState,
+    path: String,
+    start: usize,
+) -> impl futures::Stream<Item = Result<Vec<u8>, Infallible>> {
+    futures::stream::unfold(start, move |offset| {
+        let (state, path) = (state.clone(), path.clone());
+        async move {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            let chunk: Vec<u8> = state
+                .lock()
+                .unwrap()
+                .inodes
+                .get(&path)?
+                .contents
+                .as_ref()?
+                .get(offset..)?
+                .iter()
+                .take(2)
+                .copied()
+                .collect();
+            (!chunk.is_empty()).then(|| (Ok(chunk.clone()), offset + chunk.len()))
+        }
+    })
+}
+
+async fn delete_path(
+    State(state): State<SharedState>,
+    UrlPath(path): UrlPath<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if !has_token(&state, &query) {
+        return error(StatusCode::UNAUTHORIZED, "Not authenticated");
+    }
+    if state.fail_deletes {
+        return error(StatusCode::INTERNAL_SERVER_ERROR, "Delete failed");
+    }
+    let path = format!("/{path}");
+    if !state.inodes.contains_key(&path) {
+        return error(StatusCode::NOT_FOUND, "Inode not found");
+    }
+    let deleted: Vec<InodeResource> = state
+        .subtree(&path)
+        .iter()
+        .filter_map(|removed| state.resource(removed))
+        .collect();
+    for removed in state.subtree(&path) {
+        state.inodes.remove(&removed);
+    }
+    Json(deleted).into_response()
+}
+
+/// A running fake backend and FTP server
+pub struct TestServer {
+    pub state: SharedState,
+    pub ftp_address: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    service: JoinHandle<Result<(), libunftp::ServerError>>,
+}
+
+pub fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// Every server gets its own passive port range so tests can run in parallel
+fn passive_range() -> (u16, u16) {
+    static NEXT_START: AtomicU16 = AtomicU16::new(41000);
+    let start = NEXT_START.fetch_add(20, Ordering::SeqCst);
+    (start, start + 20)
+}
+
+async fn start_backend() -> (SharedState, SocketAddr) {
+    let state: SharedState = Arc::new(Mutex::new(MockState::new()));
+
+    let app = Router::new()
+        .route("/api/health", get(fss_health))
+        .route("/data/health", get(fhs_health))
+        .route("/api/user/authenticate", post(authenticate))
+        .route("/api/user/info", get(user_info))
+        .route("/api/filesystem/info", get(inode_info))
+        .route("/api/filesystem/contents", get(contents))
+        .route("/api/filesystem/folder/create", post(create_folder))
+        .route("/api/filesystem/rename", put(rename))
+        .route("/api/filesystem/move", put(move_inode))
+        .route("/api/filesystem/timestamp", put(timestamp))
+        .route("/data/upload", post(upload))
+        .route("/data/download/*path", get(download))
+        .route("/data/delete/*path", delete(delete_path))
+        .with_state(state.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service()),
+    );
+
+    (state, address)
+}
+
+/// Command line arguments pointing the FTP server at the fake backend
+pub fn args_for(backend: SocketAddr, ftp_port: u16, extra: &[&str]) -> Args {
+    let (passive_start, passive_end) = passive_range();
+    let mut args = vec![
+        "ftp-fighter".to_owned(),
+        "--hostname=127.0.0.1".to_owned(),
+        format!("--port={ftp_port}"),
+        format!("--passive-start-port={passive_start}"),
+        format!("--passive-end-port={passive_end}"),
+        format!("--backend-url=http://{backend}"),
+        format!("--filehandler-url=http://{backend}"),
+    ];
+    args.extend(extra.iter().map(|arg| (*arg).to_owned()));
+    Args::parse_from(args)
+}
+
+/// Start the fake backend and an FTP server in front of it
+pub async fn start_server(extra_args: &[&str]) -> TestServer {
+    let (state, backend) = start_backend().await;
+    let ftp_port = free_port();
+    let args = args_for(backend, ftp_port, extra_args);
+
+    let (shutdown, shutdown_signal) = oneshot::channel();
+    let service = tokio::spawn(ftp_fighter::start_ftp_service_until(args, shutdown_signal));
+
+    let ftp_address: SocketAddr = format!("127.0.0.1:{ftp_port}").parse().unwrap();
+    for _ in 0..50 {
+        if tokio::net::TcpStream::connect(ftp_address).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    TestServer {
+        state,
+        ftp_address,
+        shutdown: Some(shutdown),
+        service,
+    }
+}
+
+impl TestServer {
+    /// Act as if the process received SIGTERM
+    pub fn begin_shutdown(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            shutdown.send(()).unwrap();
+        }
+    }
+
+    /// Wait for the ftp service to stop after `begin_shutdown`
+    pub async fn stopped(self) -> Result<(), libunftp::ServerError> {
+        self.service.await.unwrap()
+    }
+
+    /// Run a blocking FTP session logged in as the test user
+    pub async fn session<T, F>(&self, f: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut suppaftp::FtpStream) -> T + Send + 'static,
+    {
+        let address = self.ftp_address;
+        tokio::task::spawn_blocking(move || {
+            let mut ftp = suppaftp::FtpStream::connect(address).unwrap();
+            ftp.login(USERNAME, PASSWORD).unwrap();
+            let result = f(&mut ftp);
+            let _ = ftp.quit();
+            result
+        })
+        .await
+        .unwrap()
+    }
+
+    pub fn add_folder(&self, path: &str) {
+        self.state.lock().unwrap().insert(path.to_owned(), None);
+    }
+
+    pub fn add_file(&self, path: &str, contents: &[u8]) {
+        self.state
+            .lock()
+            .unwrap()
+            .insert(path.to_owned(), Some(contents.to_vec()));
+    }
+
+    /// Content type the FileHandlerService received for the upload at path
+    pub fn mime_type(&self, path: &str) -> Option<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .inodes
+            .get(path)
+            .and_then(|inode| inode.mime_type.clone())
+    }
+
+    pub fn file(&self, path: &str) -> Option<Vec<u8>> {
+        self.state
+            .lock()
+            .unwrap()
+            .inodes
+            .get(path)
+            .and_then(|inode| inode.contents.clone())
+    }
+
+    pub fn set_privileges(&self, privileges: &str) {
+        self.state.lock().unwrap().privileges = privileges.to_owned();
+    }
+
+    /// Make the backend reject the current token
+    pub fn revoke_token(&self) {
+        self.state.lock().unwrap().token.clear();
+    }
+
+    pub fn authentications(&self) -> usize {
+        self.state.lock().unwrap().authentications
+    }
+
+    pub fn truncate_uploads(&self) {
+        self.state.lock().unwrap().truncate_uploads = true;
+    }
+
+    pub fn ignore_ranges(&self) {
+        self.state.lock().unwrap().ignore_ranges = true;
+    }
+
+    pub fn fail_renames(&self) {
+        self.state.lock().unwrap().fail_renames = true;
+    }
+
+    pub fn fail_moves(&self) {
+        self.state.lock().unwrap().fail_moves = true;
+    }
+
+    pub fn fail_deletes(&self) {
+        self.state.lock().unwrap().fail_deletes = true;
+    }
+
+    pub fn make_lookups_unavailable(&self, count: usize) {
+        self.state.lock().unwrap().unavailable_lookups = count;
+    }
+
+    pub fn lookups(&self) -> usize {
+        self.state.lock().unwrap().lookups
+    }
+
+    pub fn downloads(&self) -> usize {
+        self.state.lock().unwrap().downloads
+    }
+
+    pub fn user_infos(&self) -> usize {
+        self.state.lock().unwrap().user_infos
+    }
+
+    pub fn paths(&self) -> Vec<String> {
+        self.state.lock().unwrap().inodes.keys().cloned().collect()
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        self.state.lock().unwrap().inodes.contains_key(path)
+    }
+}
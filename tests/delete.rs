@@ -0,0 +1,67 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::start_server;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dele_and_rmd_check_the_inode_type() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"hello");
+
+    let (dele_folder, rmd_file) = server
+        .session(|ftp| (ftp.rm("/Home"), ftp.rmdir("/Home/notes.txt")))
+        .await;
+
+    assert!(dele_folder.unwrap_err().to_string().contains("550"));
+    assert!(rmd_file.unwrap_err().to_string().contains("550"));
+    assert!(server.exists("/Home/notes.txt"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rmd_refuses_folders_that_are_not_empty() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/Pictures");
+    server.add_file("/Home/Pictures/cat.png", b"meow");
+
+    let rmd = server.session(|ftp| ftp.rmdir("/Home/Pictures")).await;
+
+    assert!(rmd.unwrap_err().to_string().contains("550"));
+    assert!(server.exists("/Home/Pictures/cat.png"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rmd_ignores_leftovers_of_failed_uploads() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/Pictures");
+    server.add_file("/Home/Pictures/.ftp-upload-1234", b"meo");
+    server.add_file("/Home/Pictures/.ftp-replaced-5", b"meow");
+
+    server
+        .session(|ftp| ftp.rmdir("/Home/Pictures"))
+        .await
+        .unwrap();
+
+    assert!(!server.exists("/Home/Pictures"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rmd_deletes_recursively_if_configured() {
+    let server = start_server(&["--rmd-policy", "recursive"]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/Pictures");
+    server.add_folder("/Home/Pictures/Cats");
+    server.add_file("/Home/Pictures/Cats/cat.png", b"meow");
+
+    server
+        .session(|ftp| ftp.rmdir("/Home/Pictures"))
+        .await
+        .unwrap();
+
+    assert!(!server.exists("/Home/Pictures"));
+    assert!(!server.exists("/Home/Pictures/Cats/cat.png"));
+    assert!(server.exists("/Home"));
+}
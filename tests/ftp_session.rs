@@ -0,0 +1,214 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::{start_server, PASSWORD, USERNAME};
+use std::io::Cursor;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn login_works_only_with_correct_password() {
+    let server = start_server(&[]).await;
+    let address = server.ftp_address;
+
+    let (good, bad) = tokio::task::spawn_blocking(move || {
+        let mut ftp = suppaftp::FtpStream::connect(address).unwrap();
+        let good = ftp.login(USERNAME, PASSWORD);
+        let mut ftp = suppaftp::FtpStream::connect(address).unwrap();
+        let bad = ftp.login(USERNAME, "wrong");
+        (good, bad)
+    })
+    .await
+    .unwrap();
+
+    assert!(good.is_ok());
+    assert!(bad.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upload_list_and_download() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+
+    let (listing, downloaded, size) = server
+        .session(|ftp| {
+            ftp.cwd("/Home").unwrap();
+            ftp.put_file("notes.txt", &mut Cursor::new(b"hello ftp".to_vec()))
+                .unwrap();
+            let listing = ftp.nlst(None).unwrap();
+            let downloaded = ftp.retr_as_buffer("notes.txt").unwrap().into_inner();
+            let size = ftp.size("notes.txt").unwrap();
+            (listing, downloaded, size)
+        })
+        .await;
+
+    assert_eq!(listing, vec!["notes.txt"]);
+    assert_eq!(downloaded, b"hello ftp");
+    assert_eq!(size, 9);
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"hello ftp");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resumed_download_starts_at_offset() {
+    let server = start_server(&[]).await;
+    server.add_file("/archive.bin", b"0123456789");
+
+    let downloaded = server
+        .session(|ftp| {
+            ftp.resume_transfer(4).unwrap();
+            ftp.retr_as_buffer("/archive.bin").unwrap().into_inner()
+        })
+        .await;
+
+    assert_eq!(downloaded, b"456789");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resumed_download_at_the_end_is_empty_and_beyond_it_fails() {
+    let server = start_server(&[]).await;
+    server.add_file("/archive.bin", b"0123456789");
+
+    let (at_end, beyond_end) = server
+        .session(|ftp| {
+            ftp.resume_transfer(10).unwrap();
+            let at_end = ftp.retr_as_buffer("/archive.bin").unwrap().into_inner();
+            ftp.resume_transfer(11).unwrap();
+            (at_end, ftp.retr_as_buffer("/archive.bin").map(|_| ()))
+        })
+        .await;
+
+    assert!(at_end.is_empty());
+    let err = beyond_end.unwrap_err().to_string();
+    assert!(err.contains("550"), "{err}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resumed_download_skips_to_offset_if_range_is_ignored() {
+    let server = start_server(&[]).await;
+    server.add_file("/archive.bin", b"0123456789");
+    server.ignore_ranges();
+
+    let (downloaded, beyond_end) = server
+        .session(|ftp| {
+            ftp.resume_transfer(4).unwrap();
+            let downloaded = ftp.retr_as_buffer("/archive.bin").unwrap().into_inner();
+            ftp.resume_transfer(11).unwrap();
+            (downloaded, ftp.retr_as_buffer("/archive.bin").map(|_| ()))
+        })
+        .await;
+
+    assert_eq!(downloaded, b"456789");
+    let err = beyond_end.unwrap_err().to_string();
+    assert!(err.contains("550"), "{err}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resumed_upload_continues_file() {
+    let server = start_server(&[]).await;
+    server.add_file("/archive.bin", b"01234xxx");
+
+    server
+        .session(|ftp| {
+            ftp.resume_transfer(5).unwrap();
+            ftp.put_file("/archive.bin", &mut Cursor::new(b"56789".to_vec()))
+                .unwrap();
+        })
+        .await;
+
+    assert_eq!(server.file("/archive.bin").unwrap(), b"0123456789");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resumed_upload_keeps_the_mime_type_of_the_file_name() {
+    let server = start_server(&[]).await;
+    server.add_file("/notes.txt", b"hello");
+
+    server
+        .session(|ftp| {
+            ftp.resume_transfer(5).unwrap();
+            ftp.put_file("/notes.txt", &mut Cursor::new(b" ftp".to_vec()))
+                .unwrap();
+        })
+        .await;
+
+    assert_eq!(server.file("/notes.txt").unwrap(), b"hello ftp");
+    assert_eq!(server.mime_type("/notes.txt").unwrap(), "text/plain");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn directories_can_be_created_renamed_and_removed() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/a.txt", b"a");
+
+    server
+        .session(|ftp| {
+            ftp.mkdir("/Home/Docs").unwrap();
+            ftp.rename("/Home/a.txt", "/Home/Docs/b.txt").unwrap();
+            ftp.rm("/Home/Docs/b.txt").unwrap();
+            ftp.rmdir("/Home/Docs").unwrap();
+        })
+        .await;
+
+    assert!(!server.exists("/Home/a.txt"));
+    assert!(!server.exists("/Home/Docs/b.txt"));
+    assert!(!server.exists("/Home/Docs"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn missing_files_are_reported() {
+    let server = start_server(&[]).await;
+
+    let result = server
+        .session(|ftp| ftp.retr_as_buffer("/missing.txt").map(|_| ()))
+        .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn modification_times_are_reported_and_can_be_set() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"hello");
+
+    let (before, after) = server
+        .session(|ftp| {
+            let before = ftp.mdtm("/Home/notes.txt").unwrap();
+            // the way rclone sets modification times
+            ftp.mdtm("/20221003093709 /Home/notes.txt").unwrap();
+            let after = ftp.mdtm("/Home/notes.txt").unwrap();
+            (before, after)
+        })
+        .await;
+
+    assert_eq!(before.to_string(), "2020-09-13 12:26:40");
+    assert_eq!(after.to_string(), "2022-10-03 09:37:09");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn listings_show_user_and_group_names() {
+    let server = start_server(&["--group-mapping", "/Shared=team"]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Shared");
+    server.add_file("/Home/notes.txt", b"hello");
+    server.add_file("/Shared/plan.txt", b"plan");
+
+    let (home, shared) = server
+        .session(|ftp| {
+            let home = ftp.list(Some("/Home")).unwrap();
+            let shared = ftp.list(Some("/Shared")).unwrap();
+            (home, shared)
+        })
+        .await;
+
+    let fields = |line: &str| {
+        line.split_whitespace()
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>()
+    };
+    let home = fields(&home[0]);
+    assert_eq!(home[2..4], [USERNAME, USERNAME]);
+    assert_eq!(home.last().unwrap(), "notes.txt");
+    let shared = fields(&shared[0]);
+    assert_eq!(shared[2..4], [USERNAME, "team"]);
+}
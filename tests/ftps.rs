@@ -0,0 +1,52 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::{start_server, PASSWORD, USERNAME};
+use std::{fs, path::PathBuf};
+
+/// Write a self signed certificate and key to the temp dir
+fn write_certificate(name: &str) -> (PathBuf, PathBuf) {
+    let certificate = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+    let dir = std::env::temp_dir();
+    let certs_file = dir.join(format!("ftp-fighter-{name}-{}.crt", std::process::id()));
+    let key_file = dir.join(format!("ftp-fighter-{name}-{}.key", std::process::id()));
+    fs::write(&certs_file, certificate.serialize_pem().unwrap()).unwrap();
+    fs::write(&key_file, certificate.serialize_private_key_pem()).unwrap();
+    (certs_file, key_file)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn plaintext_login_is_refused_when_ftps_is_required() {
+    let (certs_file, key_file) = write_certificate("required");
+    let server = start_server(&[
+        &format!("--ftps-certs-file={}", certs_file.display()),
+        &format!("--ftps-key-file={}", key_file.display()),
+        "--ftps-required",
+    ])
+    .await;
+    let address = server.ftp_address;
+
+    let login = tokio::task::spawn_blocking(move || {
+        let mut ftp = suppaftp::FtpStream::connect(address).unwrap();
+        ftp.login(USERNAME, PASSWORD)
+    })
+    .await
+    .unwrap();
+
+    assert!(login.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn plaintext_login_works_when_ftps_is_optional() {
+    let (certs_file, key_file) = write_certificate("optional");
+    let server = start_server(&[
+        &format!("--ftps-certs-file={}", certs_file.display()),
+        &format!("--ftps-key-file={}", key_file.display()),
+    ])
+    .await;
+
+    let pwd = server.session(|ftp| ftp.pwd().unwrap()).await;
+
+    assert_eq!(pwd, "/");
+}
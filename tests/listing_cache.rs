@@ -0,0 +1,67 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::start_server;
+use std::{io::Cursor, sync::Arc};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn repeated_lookups_are_answered_from_the_cache() {
+    let server = Arc::new(start_server(&[]).await);
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"hello");
+
+    let observer = server.clone();
+    let (first, second) = server
+        .session(move |ftp| {
+            ftp.cwd("/Home").unwrap();
+            ftp.list(None).unwrap();
+            let first = observer.lookups();
+            ftp.cwd("/Home").unwrap();
+            ftp.list(None).unwrap();
+            assert_eq!(ftp.size("notes.txt").unwrap(), 5);
+            ftp.mdtm("notes.txt").unwrap();
+            (first, observer.lookups())
+        })
+        .await;
+
+    assert_eq!(first, second);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn writes_invalidate_cached_listings() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/old.txt", b"old");
+
+    let (before, after) = server
+        .session(|ftp| {
+            let before = ftp.nlst(Some("/Home")).unwrap();
+            ftp.put_file("/Home/new.txt", &mut Cursor::new(b"new".to_vec()))
+                .unwrap();
+            ftp.rename("/Home/old.txt", "/Home/renamed.txt").unwrap();
+            (before, ftp.nlst(Some("/Home")).unwrap())
+        })
+        .await;
+
+    assert_eq!(before, vec!["old.txt"]);
+    assert_eq!(after, vec!["new.txt", "renamed.txt"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cache_can_be_disabled() {
+    let server = Arc::new(start_server(&["--listing-cache-ttl", "0"]).await);
+    server.add_folder("/Home");
+
+    let observer = server.clone();
+    let (first, second) = server
+        .session(move |ftp| {
+            ftp.list(Some("/Home")).unwrap();
+            let first = observer.lookups();
+            ftp.list(Some("/Home")).unwrap();
+            (first, observer.lookups())
+        })
+        .await;
+
+    assert!(second > first);
+}
@@ -0,0 +1,55 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::{start_server, USERNAME};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn parallel_sessions_reuse_the_login() {
+    let server = start_server(&[]).await;
+
+    server.session(|ftp| ftp.pwd().unwrap()).await;
+    server.session(|ftp| ftp.pwd().unwrap()).await;
+
+    assert_eq!(server.authentications(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn cached_login_needs_the_same_password() {
+    let server = start_server(&[]).await;
+    server.session(|ftp| ftp.pwd().unwrap()).await;
+    let address = server.ftp_address;
+
+    let login = tokio::task::spawn_blocking(move || {
+        let mut ftp = suppaftp::FtpStream::connect(address).unwrap();
+        ftp.login(USERNAME, "wrong")
+    })
+    .await
+    .unwrap();
+
+    assert!(login.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn renewed_token_replaces_the_cached_one() {
+    let server = start_server(&[]).await;
+    server.session(|ftp| ftp.pwd().unwrap()).await;
+
+    server.revoke_token();
+    let renewed = server.session(|ftp| ftp.list(None).is_ok()).await;
+    let reused = server.session(|ftp| ftp.list(None).is_ok()).await;
+
+    assert!(renewed);
+    assert!(reused);
+    assert_eq!(server.authentications(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn caching_can_be_disabled() {
+    let server = start_server(&["--login-cache-ttl=0"]).await;
+
+    server.session(|ftp| ftp.pwd().unwrap()).await;
+    server.session(|ftp| ftp.pwd().unwrap()).await;
+
+    assert_eq!(server.authentications(), 2);
+}
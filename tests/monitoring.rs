@@ -0,0 +1,86 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::{free_port, start_server};
+use std::time::Duration;
+
+async fn get(url: &str) -> reqwest::Response {
+    for _ in 0..50 {
+        if let Ok(response) = reqwest::get(url).await {
+            return response;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("{url} did not respond");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn metrics_cover_logins_commands_and_backend_requests() {
+    let monitoring_port = free_port();
+    let server = start_server(&[&format!("--monitoring-port={monitoring_port}")]).await;
+    server.session(|ftp| ftp.list(None).unwrap()).await;
+
+    let response = get(&format!("http://127.0.0.1:{monitoring_port}/metrics")).await;
+    assert!(response.status().is_success());
+    let metrics = response.text().await.unwrap();
+
+    assert!(metrics.contains(r#"ftp_fighter_logins_total{result="success"}"#));
+    assert!(metrics.contains(
+        r#"ftp_fighter_backend_request_duration_seconds_count{endpoint="fss:/filesystem/contents"}"#
+    ));
+    assert!(metrics.contains("ftp_command_total"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn health_and_readiness_report_backend_status() {
+    let monitoring_port = free_port();
+    let server = start_server(&[&format!("--monitoring-port={monitoring_port}")]).await;
+    let base = format!("http://127.0.0.1:{monitoring_port}");
+
+    assert!(get(&format!("{base}/healthz")).await.status().is_success());
+
+    let ready = get(&format!("{base}/readyz")).await;
+    assert!(ready.status().is_success());
+    let body: serde_json::Value = ready.json().await.unwrap();
+    assert_eq!(body["dependencies"]["fileHandlerService"]["status"], "up");
+
+    server.state.lock().unwrap().fhs_healthy = false;
+    let not_ready = get(&format!("{base}/readyz")).await;
+    assert_eq!(not_ready.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = not_ready.json().await.unwrap();
+    assert_eq!(body["status"], "down");
+    assert_eq!(body["dependencies"]["fileSystemService"]["status"], "up");
+    assert_eq!(body["dependencies"]["fileHandlerService"]["status"], "down");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn readiness_probes_do_not_close_the_circuit_breaker() {
+    let monitoring_port = free_port();
+    let server = start_server(&[
+        &format!("--monitoring-port={monitoring_port}"),
+        "--http-retry-max-attempts",
+        "1",
+        "--http-circuit-breaker-threshold",
+        "2",
+    ])
+    .await;
+    server.add_folder("/Home");
+    server.make_lookups_unavailable(2);
+    server
+        .session(|ftp| {
+            assert!(ftp.cwd("/Home").is_err());
+            assert!(ftp.cwd("/Home").is_err());
+        })
+        .await;
+
+    let ready = get(&format!("http://127.0.0.1:{monitoring_port}/readyz")).await;
+    assert!(ready.status().is_success());
+
+    let lookups_before = server.lookups();
+    let cwd = server
+        .session(|ftp| ftp.cwd("/Home").unwrap_err().to_string())
+        .await;
+    assert!(cwd.contains("450"), "{cwd}");
+    assert_eq!(server.lookups(), lookups_before);
+}
@@ -0,0 +1,52 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::{start_server, PASSWORD, USERNAME};
+use std::io::Cursor;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn disabled_and_locked_accounts_are_refused() {
+    for privileges in ["DISABLED", "NORMAL,LOCKED"] {
+        let server = start_server(&[]).await;
+        server.set_privileges(privileges);
+        let address = server.ftp_address;
+
+        let login = tokio::task::spawn_blocking(move || {
+            let mut ftp = suppaftp::FtpStream::connect(address).unwrap();
+            ftp.login(USERNAME, PASSWORD)
+        })
+        .await
+        .unwrap();
+
+        assert!(login.is_err(), "{privileges} should be refused");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn read_only_users_can_only_read() {
+    let server = start_server(&[]).await;
+    server.set_privileges("READ_ONLY");
+    server.add_folder("/Home");
+    server.add_file("/Home/report.pdf", b"audit me");
+
+    let (downloaded, writes) = server
+        .session(|ftp| {
+            let downloaded = ftp.retr_as_buffer("/Home/report.pdf").unwrap().into_inner();
+            let writes = [
+                ftp.put_file("/Home/new.txt", &mut Cursor::new(b"x".to_vec()))
+                    .is_err(),
+                ftp.rm("/Home/report.pdf").is_err(),
+                ftp.mkdir("/Home/Docs").is_err(),
+                ftp.rmdir("/Home").is_err(),
+                ftp.rename("/Home/report.pdf", "/Home/other.pdf").is_err(),
+            ];
+            (downloaded, writes)
+        })
+        .await;
+
+    assert_eq!(downloaded, b"audit me");
+    assert_eq!(writes, [true; 5]);
+    assert!(server.exists("/Home/report.pdf"));
+    assert!(!server.exists("/Home/new.txt"));
+}
@@ -0,0 +1,124 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::start_server;
+use std::io::Cursor;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn uploads_within_the_quota_are_stored() {
+    let server = start_server(&["--user-quota", "100"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/old.bin", &[0; 90]);
+
+    server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"hello ftp".to_vec())))
+        .await
+        .unwrap();
+
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"hello ftp");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn uploads_are_refused_when_the_quota_is_used_up() {
+    let server = start_server(&["--user-quota", "100"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/old.bin", &[0; 100]);
+
+    let upload = server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"hello ftp".to_vec())))
+        .await;
+
+    let err = upload.unwrap_err().to_string();
+    assert!(err.contains("552"), "{err}");
+    assert!(!server.exists("/Home/notes.txt"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn uploads_are_aborted_once_they_exceed_the_quota() {
+    let server = start_server(&["--safe-uploads", "--user-quota", "100"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/old.bin", &[0; 96]);
+
+    let upload = server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"hello ftp".to_vec())))
+        .await;
+
+    let err = upload.unwrap_err().to_string();
+    assert!(err.contains("552"), "{err}");
+    assert_eq!(server.paths(), vec!["/", "/Home", "/Home/old.bin"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn overwritten_files_are_credited() {
+    let server = start_server(&["--user-quota", "100"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/old.bin", &[0; 91]);
+    server.add_file("/Home/notes.txt", b"old notes");
+
+    server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"new notes".to_vec())))
+        .await
+        .unwrap();
+
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"new notes");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn safe_uploads_are_not_credited_for_the_file_they_replace() {
+    let server = start_server(&["--safe-uploads", "--user-quota", "100"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/old.bin", &[0; 91]);
+    server.add_file("/Home/notes.txt", b"old notes");
+
+    let upload = server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"new notes".to_vec())))
+        .await;
+
+    let err = upload.unwrap_err().to_string();
+    assert!(err.contains("552"), "{err}");
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"old notes");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resumed_uploads_reserve_the_copied_bytes() {
+    let server = start_server(&["--user-quota", "100"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/old.bin", &[0; 82]);
+    server.add_file("/Home/notes.txt", b"hello ftx");
+
+    server
+        .session(|ftp| {
+            ftp.resume_transfer(8).unwrap();
+            ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"p".to_vec()))
+        })
+        .await
+        .unwrap();
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"hello ftp");
+
+    // the copy of the first 8 bytes leaves a single byte
+    let upload = server
+        .session(|ftp| {
+            ftp.resume_transfer(8).unwrap();
+            ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"p!".to_vec()))
+        })
+        .await;
+
+    let err = upload.unwrap_err().to_string();
+    assert!(err.contains("552"), "{err}");
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"hello ftp");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn uploads_are_not_limited_without_a_quota() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/old.bin", &[0; 1000]);
+
+    server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"hello ftp".to_vec())))
+        .await
+        .unwrap();
+
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"hello ftp");
+}
@@ -0,0 +1,168 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::start_server;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rename_moves_into_another_folder_with_a_new_name() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/Documents");
+    server.add_file("/Home/draft.txt", b"hello");
+
+    server
+        .session(|ftp| ftp.rename("/Home/draft.txt", "/Home/Documents/final.txt"))
+        .await
+        .unwrap();
+
+    assert!(!server.exists("/Home/draft.txt"));
+    assert_eq!(
+        server.file("/Home/Documents/final.txt").unwrap(),
+        b"hello".to_vec()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_move_reverts_the_rename() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/Documents");
+    server.add_file("/Home/draft.txt", b"hello");
+    server.fail_moves();
+
+    let rename = server
+        .session(|ftp| ftp.rename("/Home/draft.txt", "/Home/Documents/final.txt"))
+        .await;
+
+    assert!(rename.is_err());
+    assert!(server.exists("/Home/draft.txt"));
+    assert!(!server.exists("/Home/final.txt"));
+    assert!(!server.exists("/Home/Documents/final.txt"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_rename_reverts_the_move() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/Documents");
+    server.add_file("/Home/draft.txt", b"hello");
+    // taken in the source folder, so the file has to be moved first
+    server.add_file("/Home/final.txt", b"other");
+    server.fail_renames();
+
+    let rename = server
+        .session(|ftp| ftp.rename("/Home/draft.txt", "/Home/Documents/final.txt"))
+        .await;
+
+    assert!(rename.is_err());
+    assert!(server.exists("/Home/draft.txt"));
+    assert!(!server.exists("/Home/Documents/draft.txt"));
+    assert_eq!(server.file("/Home/final.txt").unwrap(), b"other".to_vec());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rename_onto_existing_target_is_refused_by_default() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/a.txt", b"a");
+    server.add_file("/Home/b.txt", b"b");
+
+    let rename = server
+        .session(|ftp| ftp.rename("/Home/a.txt", "/Home/b.txt"))
+        .await;
+
+    assert!(rename.unwrap_err().to_string().contains("553"));
+    assert_eq!(server.file("/Home/a.txt").unwrap(), b"a".to_vec());
+    assert_eq!(server.file("/Home/b.txt").unwrap(), b"b".to_vec());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn rename_overwrites_existing_file_if_configured() {
+    let server = start_server(&["--rename-policy", "overwrite"]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/Documents");
+    server.add_file("/Home/a.txt", b"new");
+    server.add_file("/Home/Documents/b.txt", b"old");
+
+    let (rename, names) = server
+        .session(|ftp| {
+            (
+                ftp.rename("/Home/a.txt", "/Home/Documents/b.txt"),
+                ftp.nlst(Some("/Home/Documents")),
+            )
+        })
+        .await;
+
+    rename.unwrap();
+    assert!(!server.exists("/Home/a.txt"));
+    assert_eq!(
+        server.file("/Home/Documents/b.txt").unwrap(),
+        b"new".to_vec()
+    );
+    assert_eq!(names.unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_overwrite_restores_both_files() {
+    let server = start_server(&["--rename-policy", "overwrite"]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/Documents");
+    server.add_file("/Home/a.txt", b"new");
+    server.add_file("/Home/Documents/b.txt", b"old");
+    server.fail_moves();
+
+    let rename = server
+        .session(|ftp| ftp.rename("/Home/a.txt", "/Home/Documents/b.txt"))
+        .await;
+
+    assert!(rename.is_err());
+    assert_eq!(server.file("/Home/a.txt").unwrap(), b"new".to_vec());
+    assert_eq!(
+        server.file("/Home/Documents/b.txt").unwrap(),
+        b"old".to_vec()
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leftover_backup_of_overwritten_file_is_hidden() {
+    let server = start_server(&["--rename-policy", "overwrite"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/a.txt", b"new");
+    server.add_file("/Home/b.txt", b"old");
+    server.fail_deletes();
+
+    let (rename, names) = server
+        .session(|ftp| {
+            (
+                ftp.rename("/Home/a.txt", "/Home/b.txt"),
+                ftp.nlst(Some("/Home")).unwrap(),
+            )
+        })
+        .await;
+
+    rename.unwrap();
+    assert_eq!(server.file("/Home/b.txt").unwrap(), b"new".to_vec());
+    assert_eq!(names, vec!["b.txt"]);
+    assert!(server
+        .paths()
+        .iter()
+        .any(|path| path.starts_with("/Home/.ftp-replaced-")));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn folders_are_never_overwritten() {
+    let server = start_server(&["--rename-policy", "overwrite"]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/Pictures");
+    server.add_file("/Home/Pictures/cat.png", b"meow");
+    server.add_file("/Home/a.txt", b"a");
+
+    let rename = server
+        .session(|ftp| ftp.rename("/Home/a.txt", "/Home/Pictures"))
+        .await;
+
+    assert!(rename.unwrap_err().to_string().contains("553"));
+    assert!(server.exists("/Home/Pictures/cat.png"));
+    assert!(server.exists("/Home/a.txt"));
+}
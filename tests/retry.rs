@@ -0,0 +1,60 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::start_server;
+use std::sync::Arc;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn transient_backend_failures_are_retried() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"hello");
+    server.make_lookups_unavailable(2);
+
+    let size = server.session(|ftp| ftp.size("/Home/notes.txt")).await;
+
+    assert_eq!(size.unwrap(), 5);
+    assert_eq!(server.lookups(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn retries_can_be_disabled() {
+    let server = start_server(&["--http-retry-max-attempts", "1"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"hello");
+    server.make_lookups_unavailable(1);
+
+    let size = server.session(|ftp| ftp.size("/Home/notes.txt")).await;
+
+    assert!(size.unwrap_err().to_string().contains("451"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn open_circuit_breaker_fails_fast() {
+    let server = Arc::new(
+        start_server(&[
+            "--http-retry-max-attempts",
+            "1",
+            "--http-circuit-breaker-threshold",
+            "2",
+        ])
+        .await,
+    );
+    server.add_folder("/Home");
+
+    let observer = server.clone();
+    let (failures, fast_failure, lookups_before, lookups_after) = server
+        .session(move |ftp| {
+            observer.make_lookups_unavailable(2);
+            let failures = [ftp.cwd("/Home").is_err(), ftp.cwd("/Home").is_err()];
+            let lookups_before = observer.lookups();
+            let fast_failure = ftp.cwd("/Home").unwrap_err().to_string();
+            (failures, fast_failure, lookups_before, observer.lookups())
+        })
+        .await;
+
+    assert_eq!(failures, [true, true]);
+    assert!(fast_failure.contains("450"), "{fast_failure}");
+    assert_eq!(lookups_before, lookups_after);
+}
@@ -0,0 +1,102 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::{start_server, TestServer};
+use std::io::Cursor;
+
+fn temp_files(server: &TestServer) -> Vec<String> {
+    server
+        .paths()
+        .into_iter()
+        .filter(|path| path.contains(".ftp-upload-"))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn safe_uploads_end_up_under_the_target_name() {
+    let server = start_server(&["--safe-uploads"]).await;
+    server.add_folder("/Home");
+
+    server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"hello ftp".to_vec())))
+        .await
+        .unwrap();
+
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"hello ftp");
+    assert!(temp_files(&server).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn safe_uploads_get_the_mime_type_of_the_target_name() {
+    let server = start_server(&["--safe-uploads"]).await;
+    server.add_folder("/Home");
+
+    server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"hello ftp".to_vec())))
+        .await
+        .unwrap();
+
+    assert_eq!(server.mime_type("/Home/notes.txt").unwrap(), "text/plain");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn safe_uploads_replace_existing_files() {
+    let server = start_server(&["--safe-uploads"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"old");
+
+    server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"new".to_vec())))
+        .await
+        .unwrap();
+
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"new");
+    assert_eq!(server.paths(), vec!["/", "/Home", "/Home/notes.txt"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn failed_safe_uploads_leave_the_target_untouched() {
+    let server = start_server(&["--safe-uploads"]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"old");
+    server.truncate_uploads();
+
+    let upload = server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"new".to_vec())))
+        .await;
+
+    assert!(upload.unwrap_err().to_string().contains("451"));
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"old");
+    assert!(temp_files(&server).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn safe_uploads_do_not_replace_folders() {
+    let server = start_server(&["--safe-uploads"]).await;
+    server.add_folder("/Home");
+    server.add_folder("/Home/notes.txt");
+
+    let upload = server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"new".to_vec())))
+        .await;
+
+    assert!(upload.is_err());
+    assert!(server.file("/Home/notes.txt").is_none());
+    assert!(temp_files(&server).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn temporary_uploads_are_hidden_from_listings() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"hello");
+    server.add_file("/Home/.ftp-upload-1234", b"hel");
+
+    let (names, lines) = server
+        .session(|ftp| (ftp.nlst(Some("/Home")), ftp.list(Some("/Home"))))
+        .await;
+
+    assert_eq!(names.unwrap(), vec!["notes.txt"]);
+    assert_eq!(lines.unwrap().len(), 1);
+}
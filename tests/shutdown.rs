@@ -0,0 +1,85 @@
+#![allow(clippy::unwrap_used)]
+mod common;
+
+use common::{start_server, PASSWORD, USERNAME};
+use std::{io::Write, sync::mpsc, time::Duration};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn running_upload_finishes_before_shutdown() {
+    let mut server = start_server(&[]).await;
+    server.add_folder("/");
+    let address = server.ftp_address;
+
+    let (upload_started, started) = mpsc::channel();
+    let (shutdown_began, began) = mpsc::channel();
+    let upload = tokio::task::spawn_blocking(move || {
+        let mut ftp = suppaftp::FtpStream::connect(address).unwrap();
+        ftp.login(USERNAME, PASSWORD).unwrap();
+        let mut stream = ftp.put_with_stream("/upload.txt").unwrap();
+        stream.write_all(b"first half, ").unwrap();
+        stream.flush().unwrap();
+        upload_started.send(()).unwrap();
+
+        began.recv().unwrap();
+        let mut late_ftp = suppaftp::FtpStream::connect(address).unwrap();
+        let late_login = late_ftp.login(USERNAME, PASSWORD);
+
+        stream.write_all(b"second half").unwrap();
+        ftp.finalize_put_stream(stream).unwrap();
+        late_login
+    });
+
+    tokio::task::spawn_blocking(move || started.recv().unwrap())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    server.begin_shutdown();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown_began.send(()).unwrap();
+
+    let late_login = upload.await.unwrap();
+    assert!(late_login.is_err());
+    assert_eq!(
+        server.file("/upload.txt").unwrap(),
+        b"first half, second half"
+    );
+
+    let address = server.ftp_address;
+    tokio::time::timeout(Duration::from_secs(5), server.stopped())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(tokio::net::TcpStream::connect(address).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn shutdown_deadline_closes_stuck_transfers() {
+    let mut server = start_server(&["--shutdown-grace-period", "1"]).await;
+    server.add_folder("/");
+    let address = server.ftp_address;
+
+    let (upload_started, started) = mpsc::channel();
+    let (shutdown_finished, finished) = mpsc::channel::<()>();
+    let upload = tokio::task::spawn_blocking(move || {
+        let mut ftp = suppaftp::FtpStream::connect(address).unwrap();
+        ftp.login(USERNAME, PASSWORD).unwrap();
+        let mut stream = ftp.put_with_stream("/stuck.txt").unwrap();
+        stream.write_all(b"never finished").unwrap();
+        stream.flush().unwrap();
+        upload_started.send(()).unwrap();
+        finished.recv().unwrap();
+    });
+
+    tokio::task::spawn_blocking(move || started.recv().unwrap())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    server.begin_shutdown();
+
+    tokio::time::timeout(Duration::from_secs(5), server.stopped())
+        .await
+        .unwrap()
+        .unwrap();
+    shutdown_finished.send(()).unwrap();
+    upload.await.unwrap();
+}
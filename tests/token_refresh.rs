@@ -0,0 +1,29 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::start_server;
+use std::io::Cursor;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn session_survives_an_expired_token() {
+    let server = start_server(&["--login-cache-ttl=0"]).await;
+    server.add_folder("/Home");
+    let revoking_server = server.state.clone();
+
+    let downloaded = server
+        .session(move |ftp| {
+            ftp.cwd("/Home").unwrap();
+            revoking_server.lock().unwrap().token.clear();
+            ftp.list(None).unwrap();
+            revoking_server.lock().unwrap().token.clear();
+            ftp.put_file("late.txt", &mut Cursor::new(b"still here".to_vec()))
+                .unwrap();
+            revoking_server.lock().unwrap().token.clear();
+            ftp.retr_as_buffer("late.txt").unwrap().into_inner()
+        })
+        .await;
+
+    assert_eq!(downloaded, b"still here");
+    assert_eq!(server.authentications(), 4);
+}
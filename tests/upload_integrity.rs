@@ -0,0 +1,71 @@
+#![allow(clippy::unwrap_used)]
+
+mod common;
+
+use common::start_server;
+use std::io::Cursor;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn truncated_uploads_are_reported() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.truncate_uploads();
+
+    let upload = server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"hello ftp".to_vec())))
+        .await;
+
+    let err = upload.unwrap_err().to_string();
+    assert!(err.contains("451"), "{err}");
+    // kept for inspection
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"hello ft");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn truncated_uploads_can_be_deleted() {
+    let server = start_server(&["--delete-corrupt-uploads"]).await;
+    server.add_folder("/Home");
+    server.truncate_uploads();
+
+    let upload = server
+        .session(|ftp| ftp.put_file("/Home/notes.txt", &mut Cursor::new(b"hello ftp".to_vec())))
+        .await;
+
+    assert!(upload.is_err());
+    assert!(!server.exists("/Home/notes.txt"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resumed_uploads_are_verified_with_the_offset() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"hello");
+
+    let upload = server
+        .session(|ftp| {
+            ftp.resume_transfer(5).unwrap();
+            ftp.put_file("/Home/notes.txt", &mut Cursor::new(b" ftp".to_vec()))
+        })
+        .await;
+
+    assert_eq!(upload.unwrap(), 4);
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"hello ftp");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn resumed_uploads_beyond_the_end_are_refused() {
+    let server = start_server(&[]).await;
+    server.add_folder("/Home");
+    server.add_file("/Home/notes.txt", b"hello");
+
+    let upload = server
+        .session(|ftp| {
+            ftp.resume_transfer(9).unwrap();
+            ftp.put_file("/Home/notes.txt", &mut Cursor::new(b" ftp".to_vec()))
+        })
+        .await;
+
+    assert!(upload.unwrap_err().to_string().contains("550"));
+    assert_eq!(server.file("/Home/notes.txt").unwrap(), b"hello");
+    assert_eq!(server.paths(), vec!["/", "/Home", "/Home/notes.txt"]);
+}